@@ -5,21 +5,88 @@ use log::info;
 
 use std::error::Error;
 
-use crate::models::{User, Campus, Event, Vehicle, Driver, EventData, Ride, EventInfo, ResetRequest};
+use crate::matching::{get_strategy, overflow, waitlist_head, Assignment, MatchStrategy, WaitlistPriority};
+use crate::models::{User, Campus, CampusCode, PickupPoint, Event, Vehicle, Driver, EventData, LegData, Leg, Ride, AssignmentChange, EventInfo, CarInfo, Device, GroupInvite, Invite, LoginToken, ResetRequest, Role, Verification};
 
 /// Path for the sqlite database
-const DB_PATH: &'static str = "rides.db";
+const DB_PATH: &str = "rides.db";
+
+/// Get the path of the sqlite database.
+/// Unit tests get their own database so they never touch real data
+pub(crate) fn db_path() -> String {
+    if cfg!(test) {
+        let path = std::env::temp_dir().join(format!("rides-test-{}.db", std::process::id()));
+        return path.to_string_lossy().into_owned();
+    }
+
+    DB_PATH.into()
+}
+
+/// Schema changes made after the initial schema, in order.
+/// Each one is applied once, tracked by the database's user_version
+const MIGRATIONS: &[&str] = &[
+    include_str!("./sql/migrations/01_event_strategy.sql"),
+    include_str!("./sql/migrations/02_ride_pickup_point.sql"),
+    include_str!("./sql/migrations/03_ride_groups.sql"),
+    include_str!("./sql/migrations/04_ride_driver_cancelled.sql"),
+    include_str!("./sql/migrations/05_trip_legs.sql"),
+    include_str!("./sql/migrations/06_campus_registry.sql"),
+    include_str!("./sql/migrations/07_campus_codes.sql"),
+    include_str!("./sql/migrations/08_assignment_overrides.sql"),
+    include_str!("./sql/migrations/09_waitlist.sql"),
+    include_str!("./sql/migrations/10_event_cutoffs.sql"),
+    include_str!("./sql/migrations/11_user_roles.sql"),
+    include_str!("./sql/migrations/12_event_cancellation.sql"),
+    include_str!("./sql/migrations/13_login_tokens.sql"),
+    include_str!("./sql/migrations/14_guests.sql"),
+    include_str!("./sql/migrations/15_devices.sql"),
+    include_str!("./sql/migrations/16_invites.sql"),
+    include_str!("./sql/migrations/17_unique_emails.sql"),
+    include_str!("./sql/migrations/18_group_invites.sql"),
+    include_str!("./sql/migrations/19_unique_rides.sql"),
+];
 
 /// Create database if not exists and update schema
 pub fn create_database() {
         info!("Running Database Migrations");
-        let conn = sqlite::open(DB_PATH).unwrap();
-        conn.execute(include_str!("./sql/init_database.sql")).unwrap();
+        let conn = sqlite::open(db_path()).unwrap();
+        migrate(&conn).unwrap();
+}
+
+/// Create any missing tables and apply new migrations
+pub fn migrate(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    conn.execute(include_str!("./sql/init_database.sql"))?;
+
+    let version = {
+        let mut cursor = conn.prepare("PRAGMA user_version;")?.into_cursor();
+        cursor.next()?.and_then(|row| row[0].as_integer()).unwrap_or(0) as usize
+    };
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        info!("Applying migration {}", i + 1);
+
+        // Begin Transaction
+        conn.execute("BEGIN;")?;
+        conn.execute(migration)?;
+        conn.execute(format!("PRAGMA user_version = {};", i + 1))?;
+
+        // End Transaction
+        conn.execute("COMMIT;")?;
+    }
+
+    Ok(())
 }
 
 /// Create a connection to the database
 pub fn connect() -> Connection {
-    sqlite::open(DB_PATH).unwrap()
+    sqlite::open(db_path()).unwrap()
+}
+
+/// Whether an error came from a write that broke a unique index,
+/// such as creating a user with an email that is already taken
+pub fn is_constraint_error(error: &(dyn Error + 'static)) -> bool {
+    // SQLITE_CONSTRAINT
+    error.downcast_ref::<sqlite::Error>().is_some_and(|error| error.code == Some(19))
 }
 
 // Funcions for interacting with Users
@@ -32,13 +99,25 @@ pub fn create_user(
     password: String,
     number: String
 ) -> Result<(), Box<dyn Error>> {
+    insert_user(conn, email, fullname, password, number)?;
+    Ok(())
+}
+
+/// Insert a new user, returns their id
+fn insert_user(
+    conn: &Connection,
+    email: String,
+    fullname: String,
+    password: String,
+    number: String
+) -> Result<Uuid, Box<dyn Error>> {
     info!("Creating New User: {email}");
-    let id = Uuid::new_v4().to_string();
+    let id = Uuid::new_v4();
     let hash = bcrypt::hash(password, 7)?;
 
     let mut stmt = conn.prepare(include_str!("./sql/create_user.sql"))?;
 
-    stmt.bind(1, id.as_str())?;
+    stmt.bind(1, &*id.to_string())?;
     stmt.bind(2, email.as_str())?;
     stmt.bind(3, fullname.as_str())?;
     stmt.bind(4, hash.as_str())?;
@@ -49,7 +128,7 @@ pub fn create_user(
         if state == State::Done { break; }
     }
 
-    Ok(())
+    Ok(id)
 }
 
 /// Set password for a user
@@ -76,6 +155,74 @@ pub fn set_password(
 
 }
 
+/// Create a guest who can ask for rides without an account.
+/// Returns the guest's id and the token for their private status page
+pub fn create_guest(
+    conn: &Connection,
+    fullname: String,
+    number: String
+) -> Result<(Uuid, Uuid), Box<dyn Error>> {
+    info!("Creating guest: {fullname}");
+    let id = Uuid::new_v4();
+    let token = Uuid::new_v4();
+
+    let mut stmt = conn.prepare(include_str!("./sql/create_guest.sql"))?;
+
+    stmt.bind(1, &*id.to_string())?;
+    stmt.bind(2, fullname.as_str())?;
+    stmt.bind(3, number.as_str())?;
+    stmt.bind(4, &*token.to_string())?;
+
+    loop {
+        let state = stmt.next()?;
+        if state == State::Done { break; }
+    }
+
+    Ok((id, token))
+}
+
+/// Get a guest by the token from their status page link
+pub fn get_guest(conn: &Connection, token: Uuid) -> Result<Option<User>, Box<dyn Error>> {
+    info!("Finding guest");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_guest.sql")
+    )?.into_cursor();
+
+    cursor.bind(&[Value::String(token.to_string())])?;
+
+    Ok(cursor.next()?.map(|row| row.into()))
+}
+
+/// Change what a user is allowed to do
+pub fn set_role(conn: &Connection, user_id: Uuid, role: Role) -> Result<(), Box<dyn Error>> {
+    info!("Setting user role");
+    let mut stmt = conn.prepare(include_str!("./sql/set_role.sql"))?;
+
+    let role: &'static str = role.into();
+    stmt.bind(1, role)?;
+    stmt.bind(2, &*user_id.to_string())?;
+
+    loop {
+        let state = stmt.next()?;
+        if state == State::Done { break; }
+    }
+
+    Ok(())
+}
+
+/// Get every user by name, with the name of who invited them if anyone did
+pub fn get_users(conn: &Connection) -> Result<Vec<(User, String)>, Box<dyn Error>> {
+    info!("Getting all users");
+    let mut cursor = conn.prepare(include_str!("./sql/get_users.sql"))?.into_cursor();
+
+    let mut users = Vec::new();
+    while let Some(row) = cursor.next()? {
+        users.push((row.into(), row[7].as_string().unwrap_or_default().to_string()));
+    }
+
+    Ok(users)
+}
+
 /// Search for a user by their email
 pub fn get_user_by_email(
     conn: &Connection,
@@ -115,107 +262,221 @@ pub fn get_user(
     Ok(Some(row.into()))
 }
 
-/// Get a list of all drivers for a given event
+/// Get a list of all drivers for one leg of a given event
 pub fn get_available_drivers(
     conn: &Connection,
     event_id: Uuid,
-    campus: Campus,
+    leg: Leg,
+    campus: &CampusCode,
 ) -> Result<Vec<(Driver, i64)>, Box<dyn Error>> {
     info!("Getting available drivers for event");
     let mut cursor = conn.prepare(
         include_str!("./sql/get_available_drivers.sql")
     )?.into_cursor();
 
-    let campus: &'static str = campus.into();
+    let leg: &'static str = leg.into();
 
     cursor.bind(&[
         Value::String(event_id.to_string()),
-        Value::String(campus.into())
+        Value::String(leg.into()),
+        Value::String(campus.to_string())
     ])?;
 
     let mut drivers = Vec::new();
 
     while let Some(row) = cursor.next()? {
-        drivers.push((row.into(), row[5].as_integer().unwrap()));
+        drivers.push((row.into(), row[6].as_integer().unwrap()));
     }
 
     Ok(drivers)
 }
 
-/// Get a list of all passengers of a driver for a single event
+/// Get a list of all passengers of a driver for one leg of a single event
 pub fn get_driver_passengers(
     conn: &Connection,
     event_id: Uuid,
-    driver_id: Uuid
+    driver_id: Uuid,
+    leg: Leg
 ) -> Result<Vec<(User, String)>, Box<dyn Error>> {
     info!("Get passengers for a driver");
     let mut cursor = conn.prepare(
         include_str!("./sql/get_driver_passengers.sql")
     )?.into_cursor();
 
+    let leg: &'static str = leg.into();
+
     cursor.bind(&[
         Value::String(event_id.to_string()),
-        Value::String(driver_id.to_string())
+        Value::String(driver_id.to_string()),
+        Value::String(leg.into())
     ])?;
 
     let mut passengers = Vec::new();
 
     while let Some(row) = cursor.next()? {
-        passengers.push((row.into(), row[6].as_string().unwrap().to_string()));
+        passengers.push((row.into(), row[8].as_string().unwrap().to_string()));
     }
 
     Ok(passengers)
 }
 
-/// Create a driver for an event
+/// Create a driver for one leg of an event, picking up from the given campuses
 pub fn create_driver(
     conn: &Connection,
     user_id: Uuid,
     event_id: Uuid,
     vehicle_id: Uuid,
     seats: usize,
-    campus: Campus
+    campuses: &[CampusCode],
+    leg: Leg
 ) -> Result<(), Box<dyn Error>> {
     info!("Create Driver");
     let mut stmt = conn.prepare(
         include_str!("./sql/create_driver.sql")
     )?;
 
-    let campus: &str = campus.into();
+    let mut add_campus = conn.prepare(
+        include_str!("./sql/create_driver_campus.sql")
+    )?;
 
-    stmt.bind(1, user_id.to_string().as_str())?;
-    stmt.bind(2, event_id.to_string().as_str())?;
+    let user_id = user_id.to_string();
+    let event_id = event_id.to_string();
+    let leg: &str = leg.into();
+
+    stmt.bind(1, user_id.as_str())?;
+    stmt.bind(2, event_id.as_str())?;
     stmt.bind(3, vehicle_id.to_string().as_str())?;
     stmt.bind(4, seats as i64)?;
-    stmt.bind(5, campus)?;
+    stmt.bind(5, leg)?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
 
     loop {
         let state = stmt.next()?;
         if state==State::Done { break; }
     }
 
+    for campus in campuses {
+        add_campus.reset()?;
+        add_campus.bind(1, event_id.as_str())?;
+        add_campus.bind(2, user_id.as_str())?;
+        add_campus.bind(3, leg)?;
+        add_campus.bind(4, campus.as_str())?;
+
+        loop {
+            let state = add_campus.next()?;
+            if state==State::Done { break; }
+        }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
     Ok(())
 }
 
-/// Create a ride for a given event
+/// Change the vehicle and seats for one leg a driver is driving to an event.
+/// If there are fewer seats than passengers, the overflow riders are marked as
+/// having their driver cancel so `reassign_cancelled_rides` finds them new seats.
+/// Returns whether any riders lost their seat
+pub fn update_driver(
+    conn: &Connection,
+    user_id: Uuid,
+    event_id: Uuid,
+    vehicle_id: Uuid,
+    seats: usize,
+    leg: Leg
+) -> Result<bool, Box<dyn Error>> {
+    info!("Update driver");
+    let mut update = conn.prepare(include_str!("./sql/update_driver.sql"))?;
+    let mut bump = conn.prepare(include_str!("./sql/bump_rider.sql"))?;
+    let code: &str = leg.into();
+
+    update.bind(1, vehicle_id.to_string().as_str())?;
+    update.bind(2, seats as i64)?;
+    update.bind(3, event_id.to_string().as_str())?;
+    update.bind(4, user_id.to_string().as_str())?;
+    update.bind(5, code)?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    loop {
+        let state = update.next()?;
+        if state==State::Done { break; }
+    }
+
+    let mut bumped_any = false;
+    let rides = get_driver_rides(conn, event_id, user_id, leg)?;
+
+    for ride in overflow(&rides, seats as i64) {
+        let leg: &str = ride.leg.into();
+
+        bump.reset()?;
+        bump.bind(1, ride.event_id.to_string().as_str())?;
+        bump.bind(2, ride.rider_id.to_string().as_str())?;
+        bump.bind(3, leg)?;
+
+        loop {
+            let state = bump.next()?;
+            if state==State::Done { break; }
+        }
+
+        bumped_any = true;
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(bumped_any)
+}
+
+/// Get the rides a driver is giving for one leg of an event, earliest request first
+fn get_driver_rides(conn: &Connection, event_id: Uuid, driver_id: Uuid, leg: Leg) -> Result<Vec<Ride>, Box<dyn Error>> {
+    let mut cursor = conn.prepare(include_str!("./sql/get_driver_rides.sql"))?.into_cursor();
+    let leg: &'static str = leg.into();
+    cursor.bind(&[
+        Value::String(event_id.to_string()),
+        Value::String(driver_id.to_string()),
+        Value::String(leg.into())
+    ])?;
+
+    let mut rides = Vec::new();
+
+    while let Some(row) = cursor.next()? {
+        rides.push(row.into());
+    }
+
+    Ok(rides)
+}
+
+/// Create a ride for one leg of a given event
 pub fn create_ride(
     conn: &Connection,
     user_id: Uuid,
     event_id: Uuid,
-    campus: Campus,
-    pickup_location: String
+    campus: &Campus,
+    pickup_location: String,
+    group_id: Option<Uuid>,
+    leg: Leg
 ) -> Result<(), Box<dyn Error>> {
     info!("Create Ride");
     let mut stmt = conn.prepare(
         include_str!("./sql/create_ride.sql")
     )?;
 
-    let campus: &str = campus.into();
+    let pickup_point = campus.normalize_pickup(&pickup_location).map(|point| point.name.as_str());
+    let leg: &str = leg.into();
 
     stmt.bind(1, user_id.to_string().as_str())?;
     stmt.bind(2, event_id.to_string().as_str())?;
-    stmt.bind(3, campus)?;
+    stmt.bind(3, campus.code.as_str())?;
     stmt.bind(4, pickup_location.as_str())?;
+    stmt.bind(5, pickup_point)?;
+    stmt.bind(6, group_id.map(|id| id.to_string()).as_deref())?;
+    stmt.bind(7, leg)?;
+    stmt.bind(8, Local::now().naive_local().timestamp())?;
 
     loop {
         let state = stmt.next()?;
@@ -225,107 +486,99 @@ pub fn create_ride(
     Ok(())
 }
 
-// Event Functions
-
-/// Create a new event
-pub fn create_event(
+/// Create a ride for a rider who wants to share a car on one leg.
+/// The other riders are only invited, their rides don't change until they accept
+pub fn create_group_ride(
     conn: &Connection,
-    name: String,
-    time: NaiveDateTime,
-    address1: String,
-    address2: String,
-    city: String,
-    state: String,
-    zipcode: String,
-    owner_id: Uuid
+    user_id: Uuid,
+    event_id: Uuid,
+    campus: &Campus,
+    pickup_location: String,
+    members: &[Uuid],
+    leg: Leg
 ) -> Result<(), Box<dyn Error>> {
-    info!("Create event: {name}");
-    let id = Uuid::new_v4().to_string();
-    let mut stmt = conn.prepare(include_str!("./sql/create_event.sql"))?;
+    info!("Create Group Ride");
+    let group_id = Uuid::new_v4();
 
-    stmt.bind(1, id.as_str())?;
-    stmt.bind(2, name.as_str())?;
-    stmt.bind(3, time.timestamp())?;
-    stmt.bind(4, address1.as_str())?;
-    stmt.bind(5, address2.as_str())?;
-    stmt.bind(6, city.as_str())?;
-    stmt.bind(7, state.as_str())?;
-    stmt.bind(8, zipcode.as_str())?;
-    stmt.bind(9, owner_id.to_string().as_str())?;
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
 
-    loop {
-        let state = stmt.next()?;
-        if state==State::Done { break; }
+    create_ride(conn, user_id, event_id, campus, pickup_location, Some(group_id), leg)?;
+
+    for &member in members {
+        create_group_invite(conn, event_id, leg, group_id, user_id, member)?;
     }
 
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
     Ok(())
 }
 
-/// Update an event
-pub fn update_event(
+/// Invite a user to join a group's car on one leg of an event
+pub fn create_group_invite(
     conn: &Connection,
-    id: Uuid,
-    name: String,
-    time: NaiveDateTime,
-    address1: String,
-    address2: String,
-    city: String,
-    state: String,
-    zipcode: String,
-) -> Result<(), Box<dyn Error>> {
-    info!("Update event: {name}");
-    let mut stmt = conn.prepare(include_str!("./sql/update_event.sql"))?;
+    event_id: Uuid,
+    leg: Leg,
+    group_id: Uuid,
+    invited_by: Uuid,
+    user_id: Uuid
+) -> Result<Uuid, Box<dyn Error>> {
+    info!("Create group invite");
+    let id = Uuid::new_v4();
+    let mut stmt = conn.prepare(include_str!("./sql/create_group_invite.sql"))?;
+    let leg: &str = leg.into();
 
-    stmt.bind(1, name.as_str())?;
-    stmt.bind(2, time.timestamp())?;
-    stmt.bind(3, address1.as_str())?;
-    stmt.bind(4, address2.as_str())?;
-    stmt.bind(5, city.as_str())?;
-    stmt.bind(6, state.as_str())?;
-    stmt.bind(7, zipcode.as_str())?;
-    stmt.bind(8, id.to_string().as_str())?;
+    stmt.bind(1, id.to_string().as_str())?;
+    stmt.bind(2, event_id.to_string().as_str())?;
+    stmt.bind(3, leg)?;
+    stmt.bind(4, group_id.to_string().as_str())?;
+    stmt.bind(5, invited_by.to_string().as_str())?;
+    stmt.bind(6, user_id.to_string().as_str())?;
+    stmt.bind(7, Local::now().naive_local().timestamp())?;
 
     loop {
         let state = stmt.next()?;
         if state==State::Done { break; }
     }
-    Ok(())
+
+    Ok(id)
 }
 
-/// Get a list of upcoming events
-pub fn get_events(conn: &Connection) -> Result<Vec<Event>, Box<dyn Error>> {
-    info!("Get events");
+/// Get a group invite by id
+pub fn get_group_invite(conn: &Connection, id: Uuid) -> Result<Option<GroupInvite>, Box<dyn Error>> {
+    info!("Get group invite");
     let mut cursor = conn.prepare(
-        include_str!("./sql/get_events.sql")
+        include_str!("./sql/get_group_invite.sql")
     )?.into_cursor();
 
-    let mut events = Vec::new(); while let Some(row) = cursor.next()? {
-        events.push(row.into());
-    }
+    cursor.bind(&[Value::String(id.to_string())])?;
 
-    Ok(events)
+    Ok(cursor.next()?.map(|row| row.into()))
 }
 
-// Vehicles functions
+/// Get the group invites a user hasn't answered for events that are still on
+pub fn get_user_group_invites(conn: &Connection, user_id: Uuid) -> Result<Vec<GroupInvite>, Box<dyn Error>> {
+    info!("Get group invites for user");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_user_group_invites.sql")
+    )?.into_cursor();
 
-/// Create a new vehicle for a driver
-pub fn create_vehicle(
-    conn: &Connection,
-    user_id: Uuid,
-    color: String,
-    make: String,
-    model: String
-) -> Result<(), Box<dyn Error>> {
-    info!("Create vehicle: {make} {model}");
-    let id = Uuid::new_v4().to_string();
+    cursor.bind(&[Value::String(user_id.to_string())])?;
 
-    let mut stmt = conn.prepare(include_str!("./sql/create_vehicle.sql"))?;
+    let mut invites = Vec::new();
+    while let Some(row) = cursor.next()? {
+        invites.push(row.into());
+    }
 
-    stmt.bind(1, id.as_str())?;
-    stmt.bind(2, user_id.to_string().as_str())?;
-    stmt.bind(3, color.as_str())?;
-    stmt.bind(4, make.as_str())?;
-    stmt.bind(5, model.as_str())?;
+    Ok(invites)
+}
+
+/// Remove a group invite once it is answered
+pub fn delete_group_invite(conn: &Connection, id: Uuid) -> Result<(), Box<dyn Error>> {
+    info!("Delete group invite");
+    let mut stmt = conn.prepare(include_str!("./sql/delete_group_invite.sql"))?;
+    stmt.bind(1, id.to_string().as_str())?;
 
     loop {
         let state = stmt.next()?;
@@ -335,357 +588,1690 @@ pub fn create_vehicle(
     Ok(())
 }
 
-/// Get all vehicles for a driver
-pub fn get_driver_vehicles(
+/// Put the invited user in the group's car, creating a ride with the group's pickup
+/// if they don't have one. The whole group waits for a driver so it gets matched together
+pub fn accept_group_invite(
     conn: &Connection,
-    driver_id: Uuid
-) -> Result<Vec<Vehicle>, Box<dyn Error>> {
-    info!("Get driver's vehicles");
-    let mut cursor = conn.prepare(
-        include_str!("./sql/get_driver_vehicles.sql")
-    )?.into_cursor();
+    invite: &GroupInvite,
+    campus: &Campus,
+    pickup_location: String
+) -> Result<(), Box<dyn Error>> {
+    info!("Accept group invite");
 
-    cursor.bind(&[Value::String(driver_id.to_string())])?;
-    let mut vehicles = Vec::new();
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
 
-    while let Some(row) = cursor.next()? {
-        vehicles.push(row.into());
+    if get_ride(conn, invite.event_id, invite.user_id, invite.leg)?.is_none() {
+        create_ride(conn, invite.user_id, invite.event_id, campus, pickup_location, Some(invite.group_id), invite.leg)?;
     }
 
-    Ok(vehicles)
-}
+    let mut stmt = conn.prepare(include_str!("./sql/join_ride_group.sql"))?;
+    let leg: &str = invite.leg.into();
 
-/// Get vehicle information from its id
-pub fn get_vehicle(
-    conn: &Connection,
-    id: Uuid
-) -> Result<Option<Vehicle>, Box<dyn Error>> {
-    info!("Get vehicle: {id}");
+    stmt.bind(1, invite.group_id.to_string().as_str())?;
+    stmt.bind(2, campus.code.as_str())?;
+    stmt.bind(3, invite.event_id.to_string().as_str())?;
+    stmt.bind(4, invite.user_id.to_string().as_str())?;
+    stmt.bind(5, leg)?;
 
-    let mut cursor = conn.prepare(
-        include_str!("./sql/get_vehicle.sql")
-    )?.into_cursor();
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
 
-    cursor.bind(&[Value::String(id.to_string())])?;
+    delete_group_invite(conn, invite.id)?;
 
-    let row = cursor.next()?;
-    if row.is_none() { return Ok(None) };
-    let row = row.unwrap();
+    // End Transaction
+    conn.execute("COMMIT;")?;
 
-    Ok(Some(row.into()))
+    Ok(())
 }
 
-/// Get all events that a user is driving
-pub fn get_driver_events(conn: &Connection, driver_id: Uuid) -> Result<Vec<Event>, Box<dyn Error>> {
-    info!("Get driver events");
+/// Change where a rider is picked up for every leg of an event.
+/// Changing campus also moves anyone grouped with the rider, and they all
+/// wait for a new driver since theirs may not serve the new campus.
+/// Riders keep their place on the waitlist
+pub fn update_ride(
+    conn: &Connection,
+    user_id: Uuid,
+    event_id: Uuid,
+    campus: &Campus,
+    pickup_location: String
+) -> Result<(), Box<dyn Error>> {
+    info!("Update ride");
+    let current = match get_ride(conn, event_id, user_id, Leg::To)? {
+        Some(ride) => ride,
+        None => match get_ride(conn, event_id, user_id, Leg::Return)? {
+            Some(ride) => ride,
+            None => return Ok(())
+        }
+    };
+
+    let user_id = user_id.to_string();
+    let event_id = event_id.to_string();
+    let pickup_point = campus.normalize_pickup(&pickup_location).map(|point| point.name.as_str());
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    if current.campus != campus.code {
+        let mut stmt = conn.prepare(include_str!("./sql/change_ride_campus.sql"))?;
+        stmt.bind(1, campus.code.as_str())?;
+        stmt.bind(2, event_id.as_str())?;
+        stmt.bind(3, user_id.as_str())?;
+
+        loop {
+            let state = stmt.next()?;
+            if state==State::Done { break; }
+        }
+    }
+
+    let mut stmt = conn.prepare(include_str!("./sql/update_ride_pickup.sql"))?;
+    stmt.bind(1, pickup_location.as_str())?;
+    stmt.bind(2, pickup_point)?;
+    stmt.bind(3, event_id.as_str())?;
+    stmt.bind(4, user_id.as_str())?;
+
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(())
+}
+
+/// Get a rider's ride for one leg of an event
+pub fn get_ride(conn: &Connection, event_id: Uuid, rider_id: Uuid, leg: Leg) -> Result<Option<Ride>, Box<dyn Error>> {
+    info!("Get ride");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_ride.sql")
+    )?.into_cursor();
+
+    let leg: &'static str = leg.into();
+
+    cursor.bind(&[
+        Value::String(event_id.to_string()),
+        Value::String(rider_id.to_string()),
+        Value::String(leg.into())
+    ])?;
+
+    let row = cursor.next()?;
+    if row.is_none() { return Ok(None) };
+    let row = row.unwrap();
+
+    Ok(Some(row.into()))
+}
+
+// Campus Functions
+
+/// Get every campus along with its pickup points
+pub fn get_campuses(conn: &Connection) -> Result<Vec<Campus>, Box<dyn Error>> {
+    info!("Get campuses");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_campuses.sql")
+    )?.into_cursor();
+
+    let mut campuses: Vec<Campus> = Vec::new();
+    while let Some(row) = cursor.next()? {
+        campuses.push(row.into());
+    }
+
+    for campus in campuses.iter_mut() {
+        campus.pickup_points = get_pickup_points(conn, &campus.code)?;
+    }
+
+    Ok(campuses)
+}
+
+/// Get a campus and its pickup points by its code
+pub fn get_campus(conn: &Connection, code: &CampusCode) -> Result<Option<Campus>, Box<dyn Error>> {
+    info!("Get campus: {code}");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_campus.sql")
+    )?.into_cursor();
+
+    cursor.bind(&[Value::String(code.to_string())])?;
+
+    let row = cursor.next()?;
+    if row.is_none() { return Ok(None) };
+    let mut campus: Campus = row.unwrap().into();
+
+    campus.pickup_points = get_pickup_points(conn, code)?;
+
+    Ok(Some(campus))
+}
+
+/// Get all pickup points on a campus
+fn get_pickup_points(conn: &Connection, campus: &CampusCode) -> Result<Vec<PickupPoint>, Box<dyn Error>> {
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_pickup_points.sql")
+    )?.into_cursor();
+
+    cursor.bind(&[Value::String(campus.to_string())])?;
+
+    let mut points = Vec::new();
+    while let Some(row) = cursor.next()? {
+        points.push(row.into());
+    }
+
+    Ok(points)
+}
+
+/// Create a new campus
+pub fn create_campus(conn: &Connection, code: &CampusCode, name: String) -> Result<(), Box<dyn Error>> {
+    info!("Create campus: {code}");
+    let mut stmt = conn.prepare(include_str!("./sql/create_campus.sql"))?;
+
+    stmt.bind(1, code.as_str())?;
+    stmt.bind(2, name.as_str())?;
+
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
+
+    Ok(())
+}
+
+/// Count the rides and drives for upcoming events that use a campus
+pub fn count_campus_signups(conn: &Connection, code: &CampusCode) -> Result<i64, Box<dyn Error>> {
+    info!("Count sign ups for campus: {code}");
+    let mut stmt = conn.prepare(include_str!("./sql/count_campus_signups.sql"))?;
+
+    stmt.bind(1, code.as_str())?;
+    stmt.bind(2, Local::now().naive_local().timestamp())?;
+    stmt.next()?;
+
+    Ok(stmt.read::<i64>(0)?)
+}
+
+/// Delete a campus along with its pickup points and any
+/// driver campuses left over from past events
+pub fn delete_campus(conn: &Connection, code: &CampusCode) -> Result<(), Box<dyn Error>> {
+    info!("Delete campus: {code}");
+    let mut remove_points = conn.prepare(include_str!("./sql/delete_campus_pickup_points.sql"))?;
+    let mut remove_drivers = conn.prepare(include_str!("./sql/delete_campus_driver_campuses.sql"))?;
+    let mut stmt = conn.prepare(include_str!("./sql/delete_campus.sql"))?;
+
+    remove_points.bind(1, code.as_str())?;
+    remove_drivers.bind(1, code.as_str())?;
+    stmt.bind(1, code.as_str())?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    loop {
+        let state = remove_points.next()?;
+        if state==State::Done { break; }
+    }
+
+    loop {
+        let state = remove_drivers.next()?;
+        if state==State::Done { break; }
+    }
+
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(())
+}
+
+/// Add a pickup point to a campus.
+/// Aliases are comma separated
+pub fn create_pickup_point(
+    conn: &Connection,
+    campus: &CampusCode,
+    name: String,
+    area: String,
+    aliases: String
+) -> Result<(), Box<dyn Error>> {
+    info!("Create pickup point: {name}");
+    let mut stmt = conn.prepare(include_str!("./sql/create_pickup_point.sql"))?;
+
+    stmt.bind(1, campus.as_str())?;
+    stmt.bind(2, name.as_str())?;
+    stmt.bind(3, area.as_str())?;
+    stmt.bind(4, aliases.to_lowercase().as_str())?;
+
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
+
+    Ok(())
+}
+
+/// Remove a pickup point from a campus
+pub fn delete_pickup_point(conn: &Connection, campus: &CampusCode, name: &str) -> Result<(), Box<dyn Error>> {
+    info!("Delete pickup point: {name}");
+    let mut stmt = conn.prepare(include_str!("./sql/delete_pickup_point.sql"))?;
+
+    stmt.bind(1, campus.as_str())?;
+    stmt.bind(2, name)?;
+
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
+
+    Ok(())
+}
+
+// Event Functions
+
+/// Create a new event
+#[allow(clippy::too_many_arguments)]
+pub fn create_event(
+    conn: &Connection,
+    name: String,
+    time: NaiveDateTime,
+    address1: String,
+    address2: String,
+    city: String,
+    state: String,
+    zipcode: String,
+    owner_id: Uuid,
+    strategy: Option<String>,
+    signup_deadline: Option<NaiveDateTime>,
+    freeze_time: Option<NaiveDateTime>
+) -> Result<(), Box<dyn Error>> {
+    info!("Create event: {name}");
+    let id = Uuid::new_v4().to_string();
+    let mut stmt = conn.prepare(include_str!("./sql/create_event.sql"))?;
+
+    stmt.bind(1, id.as_str())?;
+    stmt.bind(2, name.as_str())?;
+    stmt.bind(3, time.timestamp())?;
+    stmt.bind(4, address1.as_str())?;
+    stmt.bind(5, address2.as_str())?;
+    stmt.bind(6, city.as_str())?;
+    stmt.bind(7, state.as_str())?;
+    stmt.bind(8, zipcode.as_str())?;
+    stmt.bind(9, owner_id.to_string().as_str())?;
+    stmt.bind(10, strategy.as_deref())?;
+    stmt.bind(11, signup_deadline.map(|time| time.timestamp()))?;
+    stmt.bind(12, freeze_time.map(|time| time.timestamp()))?;
+
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
+
+    Ok(())
+}
+
+/// Update an event
+#[allow(clippy::too_many_arguments)]
+pub fn update_event(
+    conn: &Connection,
+    id: Uuid,
+    name: String,
+    time: NaiveDateTime,
+    address1: String,
+    address2: String,
+    city: String,
+    state: String,
+    zipcode: String,
+    strategy: Option<String>,
+    signup_deadline: Option<NaiveDateTime>,
+    freeze_time: Option<NaiveDateTime>
+) -> Result<(), Box<dyn Error>> {
+    info!("Update event: {name}");
+    let mut stmt = conn.prepare(include_str!("./sql/update_event.sql"))?;
+
+    stmt.bind(1, name.as_str())?;
+    stmt.bind(2, time.timestamp())?;
+    stmt.bind(3, address1.as_str())?;
+    stmt.bind(4, address2.as_str())?;
+    stmt.bind(5, city.as_str())?;
+    stmt.bind(6, state.as_str())?;
+    stmt.bind(7, zipcode.as_str())?;
+    stmt.bind(8, strategy.as_deref())?;
+    stmt.bind(9, signup_deadline.map(|time| time.timestamp()))?;
+    stmt.bind(10, freeze_time.map(|time| time.timestamp()))?;
+    stmt.bind(11, id.to_string().as_str())?;
+
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
+    Ok(())
+}
+
+/// Get everyone riding or driving to an event who can be emailed, so not guests
+pub fn get_event_attendees(conn: &Connection, event_id: Uuid) -> Result<Vec<User>, Box<dyn Error>> {
+    info!("Get attendees for event");
+    let mut cursor = conn.prepare(include_str!("./sql/get_event_attendees.sql"))?.into_cursor();
+    cursor.bind(&[Value::String(event_id.to_string())])?;
+
+    let mut users = Vec::new();
+    while let Some(row) = cursor.next()? {
+        users.push(row.into());
+    }
+
+    Ok(users)
+}
+
+/// Get a list of upcoming events
+pub fn get_events(conn: &Connection) -> Result<Vec<Event>, Box<dyn Error>> {
+    info!("Get events");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_events.sql")
+    )?.into_cursor();
+
+    let mut events = Vec::new(); while let Some(row) = cursor.next()? {
+        events.push(row.into());
+    }
+
+    Ok(events)
+}
+
+/// Get events that were called off, most recent first
+pub fn get_cancelled_events(conn: &Connection) -> Result<Vec<Event>, Box<dyn Error>> {
+    info!("Get cancelled events");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_cancelled_events.sql")
+    )?.into_cursor();
+
+    let mut events = Vec::new();
+    while let Some(row) = cursor.next()? {
+        events.push(row.into());
+    }
+
+    Ok(events)
+}
+
+/// Call off an event for everyone.
+/// The event is kept as a record of who cancelled it and when,
+/// but every ride and drive for it is removed
+pub fn cancel_event(conn: &Connection, event_id: Uuid, cancelled_by: Uuid) -> Result<(), Box<dyn Error>> {
+    info!("Cancelling event: {event_id}");
+
+    let mut cancel = conn.prepare(include_str!("./sql/cancel_event.sql"))?;
+    let mut remove_rides = conn.prepare(include_str!("./sql/delete_event_rides.sql"))?;
+    let mut remove_drivers = conn.prepare(include_str!("./sql/delete_event_drivers.sql"))?;
+    let mut remove_driver_campuses = conn.prepare(include_str!("./sql/delete_event_driver_campuses.sql"))?;
+
+    let event_id = event_id.to_string();
+
+    cancel.bind(1, Local::now().naive_local().timestamp())?;
+    cancel.bind(2, &*cancelled_by.to_string())?;
+    cancel.bind(3, event_id.as_str())?;
+    remove_rides.bind(1, event_id.as_str())?;
+    remove_drivers.bind(1, event_id.as_str())?;
+    remove_driver_campuses.bind(1, event_id.as_str())?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    for stmt in [&mut cancel, &mut remove_rides, &mut remove_drivers, &mut remove_driver_campuses] {
+        loop {
+            let state = stmt.next()?;
+            if state == State::Done { break; }
+        }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(())
+}
+
+/// Get an event by its id
+pub fn get_event(conn: &Connection, id: Uuid) -> Result<Option<Event>, Box<dyn Error>> {
+    info!("Get event: {id}");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_event.sql")
+    )?.into_cursor();
+
+    cursor.bind(&[Value::String(id.to_string())])?;
+
+    let row = cursor.next()?;
+    if row.is_none() { return Ok(None) };
+    let row = row.unwrap();
+
+    Ok(Some(row.into()))
+}
+
+// Vehicles functions
+
+/// Create a new vehicle for a driver
+pub fn create_vehicle(
+    conn: &Connection,
+    user_id: Uuid,
+    color: String,
+    make: String,
+    model: String
+) -> Result<(), Box<dyn Error>> {
+    info!("Create vehicle: {make} {model}");
+    let id = Uuid::new_v4().to_string();
+
+    let mut stmt = conn.prepare(include_str!("./sql/create_vehicle.sql"))?;
+
+    stmt.bind(1, id.as_str())?;
+    stmt.bind(2, user_id.to_string().as_str())?;
+    stmt.bind(3, color.as_str())?;
+    stmt.bind(4, make.as_str())?;
+    stmt.bind(5, model.as_str())?;
+
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
+
+    Ok(())
+}
+
+/// Get all vehicles for a driver
+pub fn get_driver_vehicles(
+    conn: &Connection,
+    driver_id: Uuid
+) -> Result<Vec<Vehicle>, Box<dyn Error>> {
+    info!("Get driver's vehicles");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_driver_vehicles.sql")
+    )?.into_cursor();
+
+    cursor.bind(&[Value::String(driver_id.to_string())])?;
+    let mut vehicles = Vec::new();
+
+    while let Some(row) = cursor.next()? {
+        vehicles.push(row.into());
+    }
+
+    Ok(vehicles)
+}
+
+/// Get vehicle information from its id
+pub fn get_vehicle(
+    conn: &Connection,
+    id: Uuid
+) -> Result<Option<Vehicle>, Box<dyn Error>> {
+    info!("Get vehicle: {id}");
+
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_vehicle.sql")
+    )?.into_cursor();
+
+    cursor.bind(&[Value::String(id.to_string())])?;
+
+    let row = cursor.next()?;
+    if row.is_none() { return Ok(None) };
+    let row = row.unwrap();
+
+    Ok(Some(row.into()))
+}
+
+/// Update a vehicle's details
+pub fn update_vehicle(
+    conn: &Connection,
+    id: Uuid,
+    color: String,
+    make: String,
+    model: String
+) -> Result<(), Box<dyn Error>> {
+    info!("Update vehicle: {id}");
+    let mut stmt = conn.prepare(include_str!("./sql/update_vehicle.sql"))?;
+
+    stmt.bind(1, color.as_str())?;
+    stmt.bind(2, make.as_str())?;
+    stmt.bind(3, model.as_str())?;
+    stmt.bind(4, id.to_string().as_str())?;
+
+    loop {
+        let state = stmt.next()?;
+        if state==State::Done { break; }
+    }
+
+    Ok(())
+}
+
+/// Get the drives for upcoming events that use a vehicle
+pub fn get_vehicle_drivers(conn: &Connection, vehicle_id: Uuid) -> Result<Vec<Driver>, Box<dyn Error>> {
+    info!("Get drives for vehicle: {vehicle_id}");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_vehicle_drivers.sql")
+    )?.into_cursor();
+
+    cursor.bind(&[
+        Value::String(vehicle_id.to_string()),
+        Value::Integer(Local::now().naive_local().timestamp())
+    ])?;
+
+    let mut drivers = Vec::new();
+    while let Some(row) = cursor.next()? {
+        drivers.push(row.into());
+    }
+
+    Ok(drivers)
+}
+
+/// Delete a vehicle.
+/// Drives using the vehicle are moved to the replacement vehicle, if there is one
+pub fn delete_vehicle(conn: &Connection, id: Uuid, replacement: Option<Uuid>) -> Result<(), Box<dyn Error>> {
+    info!("Delete vehicle: {id}");
+    let id = id.to_string();
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    if let Some(replacement) = replacement {
+        let mut replace = conn.prepare(include_str!("./sql/replace_vehicle.sql"))?;
+        replace.bind(1, replacement.to_string().as_str())?;
+        replace.bind(2, id.as_str())?;
+
+        loop {
+            let state = replace.next()?;
+            if state==State::Done { break; }
+        }
+    }
+
+    let mut remove = conn.prepare(include_str!("./sql/delete_vehicle.sql"))?;
+    remove.bind(1, id.as_str())?;
+
+    loop {
+        let state = remove.next()?;
+        if state==State::Done { break; }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(())
+}
+
+/// Get all events that a user is driving
+pub fn get_driver_events(conn: &Connection, driver_id: Uuid) -> Result<Vec<Event>, Box<dyn Error>> {
+    info!("Get driver events");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_driver_events.sql")
+    )?.into_cursor();
+    cursor.bind(&[Value::String(driver_id.to_string())])?;
+
+    let mut events = vec![];
+
+    while let Some(row) = cursor.next()? {
+        events.push(row.into());
+    }
+
+    Ok(events)
+}
+
+/// Get all events a user is getting a ride
+pub fn get_rider_events(conn: &Connection, rider_id: Uuid) -> Result<Vec<Event>, Box<dyn Error>> {
+    info!("get rider events");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_rider_events.sql")
+    )?.into_cursor();
+    cursor.bind(&[Value::String(rider_id.to_string())])?;
+
+    let mut events = vec![];
+
+    while let Some(row) = cursor.next()? {
+        events.push(row.into());
+    }
+
+    Ok(events)
+}
+
+/// Delete a user from an event, whether they are a rider or a driver.
+/// Does not delete events for everyone, only removes a user from it
+pub fn delete_user_event(conn: &Connection, user_id: Uuid, event_id: Uuid) -> Result<(), Box<dyn Error>> {
+    info!("Removing user from event");
+
+    let mut remove_rides = conn.prepare(
+        include_str!("./sql/delete_user_rides.sql")
+    )?;
+
+    let mut remove_drives = conn.prepare(
+        include_str!("./sql/delete_user_rides_drives.sql")
+    )?;
+
+    let mut remove_drivers = conn.prepare(
+        include_str!("./sql/delete_user_drives.sql")
+    )?;
+
+    let mut remove_driver_campuses = conn.prepare(
+        include_str!("./sql/delete_user_driver_campuses.sql")
+    )?;
+
+    let user_id = user_id.to_string();
+    let event_id = event_id.to_string();
+
+    remove_rides.bind(1, user_id.as_str())?;
+    remove_rides.bind(2, event_id.as_str())?;
+
+    remove_drives.bind(1, user_id.as_str())?;
+    remove_drives.bind(2, event_id.as_str())?;
+
+    remove_drivers.bind(1, user_id.as_str())?;
+    remove_drivers.bind(2, event_id.as_str())?;
+
+    remove_driver_campuses.bind(1, user_id.as_str())?;
+    remove_driver_campuses.bind(2, event_id.as_str())?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    // Remove Rides
+    loop {
+        let state = remove_rides.next()?;
+        if state==State::Done { break; }
+    }
+
+    // Remove Drives
+    loop {
+        let state = remove_drives.next()?;
+        if state==State::Done { break; }
+    }
+
+    // Remove drivers
+    loop {
+        let state = remove_drivers.next()?;
+        if state == State::Done { break; }
+    }
+
+    // Remove the campuses drivers served
+    loop {
+        let state = remove_driver_campuses.next()?;
+        if state == State::Done { break; }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(())
+}
+
+/// Get Driver information for one leg of an event
+fn get_event_driver(conn: &Connection, event_id: Uuid, user_id: Uuid, leg: Leg) -> Result<Option<(User, Vehicle)>, Box<dyn Error>> {
+    info!("Get driver info for event");
+    let mut cursor = conn.prepare(include_str!("./sql/get_event_driver.sql"))?.into_cursor();
+
+    let leg: &'static str = leg.into();
+    cursor.bind(&[
+        Value::String(event_id.to_string()),
+        Value::String(user_id.to_string()),
+        Value::String(leg.into())
+    ])?;
+    let row = cursor.next()?;
+
+    Ok(row.map(|row| {
+        (row.into(), row[7..].into())
+    }))
+}
+
+/// Get all information about an event for a given user
+pub fn get_events_data(
+    conn: &Connection,
+    user_id: Uuid,
+    priority: WaitlistPriority
+) -> Result<Vec<EventData>, Box<dyn Error>> {
+    info!("Get event info for user");
+    let mut event_data: Vec<EventData> = Vec::new();
+
+    let mut events = get_rider_events(conn, user_id)?;
+    events.extend(get_driver_events(conn, user_id)?);
+
+    for event in events {
+        if event_data.iter().any(|ed| ed.event.id == event.id) {
+            continue;
+        }
+
+        let mut legs = Vec::new();
+        for leg in [Leg::To, Leg::Return] {
+            let driving = get_user_drivers(conn, event.id, user_id)?
+                .iter()
+                .any(|driver| driver.leg == leg);
+
+            if driving {
+                let riders = get_driver_passengers(conn, event.id, user_id, leg)?;
+                legs.push(LegData {
+                    leg,
+                    riders: Some(riders),
+                    driver: None,
+                    is_driver: true,
+                    driver_cancelled: false,
+                    waitlist_position: None
+                });
+            } else if let Some(ride) = get_ride(conn, event.id, user_id, leg)? {
+                let driver = get_event_driver(conn, event.id, user_id, leg)?;
+                let waitlist_position = if driver.is_none() {
+                    waitlist_position(conn, &ride, priority)?
+                } else {
+                    None
+                };
+
+                legs.push(LegData {
+                    leg,
+                    driver,
+                    riders: None,
+                    is_driver: false,
+                    driver_cancelled: ride.driver_cancelled,
+                    waitlist_position
+                });
+            }
+        }
+
+        event_data.push(EventData { event, legs });
+    }
+
+    // Sort by event date
+    event_data.sort_by_cached_key(|ed| {
+        ed.event.time.timestamp()
+    });
+
+    Ok(event_data)
+}
+
+/// Get a rider's place in line for a seat among riders waiting on the same campus
+fn waitlist_position(conn: &Connection, ride: &Ride, priority: WaitlistPriority) -> Result<Option<usize>, Box<dyn Error>> {
+    let mut waiting = unassigned_campus_riders(conn, ride.event_id, ride.leg, &ride.campus)?;
+    priority.sort(&mut waiting);
+
+    Ok(waiting.iter()
+        .position(|waiting| waiting.rider_id == ride.rider_id)
+        .map(|i| i + 1))
+}
+
+/// Get every leg a user is driving for an event
+pub fn get_user_drivers(conn: &Connection, event_id: Uuid, user_id: Uuid) -> Result<Vec<Driver>, Box<dyn Error>> {
+    info!("Get user's drives for event");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_user_drivers.sql")
+    )?.into_cursor();
+
+    cursor.bind(&[
+        Value::String(event_id.to_string()),
+        Value::String(user_id.to_string())
+    ])?;
+
+    let mut drivers = Vec::new();
+
+    while let Some(row) = cursor.next()? {
+        drivers.push(row.into());
+    }
+
+    Ok(drivers)
+}
+
+/// Delete old events in the database
+pub fn delete_old_events(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    info!("delete old events");
+    let mut count_rides = conn.prepare(
+        include_str!("./sql/count_rides_taken.sql")
+    )?;
+
+    let mut remove_events = conn.prepare(
+        include_str!("./sql/delete_old_events.sql")
+    )?;
+
+    let mut remove_guests = conn.prepare(
+        include_str!("./sql/delete_old_guests.sql")
+    )?;
+
+    let mut remove_group_invites = conn.prepare(
+        include_str!("./sql/delete_old_group_invites.sql")
+    )?;
+
+    let expire_time = (Local::now() - chrono::Duration::days(1)).timestamp();
+    count_rides.bind(1, expire_time)?;
+    remove_events.bind(1, expire_time)?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    // Remember who got rides before the events are gone
+    loop {
+        let state = count_rides.next()?;
+        if state==State::Done { break; }
+    }
+
+    loop {
+        let state = remove_events.next()?;
+        if state==State::Done { break; }
+    }
+
+    // Guests only exist for the events they asked for rides to
+    loop {
+        let state = remove_guests.next()?;
+        if state==State::Done { break; }
+    }
+
+    loop {
+        let state = remove_group_invites.next()?;
+        if state==State::Done { break; }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(())
+}
+
+/// Pair riders with rides.
+/// Uses the event's own strategy if it has one, otherwise the default.
+/// Riders are given seats in the order of the waitlist.
+/// Returns the assignments that were made
+pub fn match_rides(
+    conn: &Connection,
+    default: &dyn MatchStrategy,
+    priority: WaitlistPriority
+) -> Result<Vec<Assignment>, Box<dyn Error>> {
+    info!("Match riders with drivers");
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    let assignments = run_matching(conn, default, priority)?;
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+    Ok(assignments)
+}
+
+/// Get the assignments `match_rides` would make right now without making them
+pub fn preview_matches(
+    conn: &Connection,
+    default: &dyn MatchStrategy,
+    priority: WaitlistPriority
+) -> Result<Vec<Assignment>, Box<dyn Error>> {
+    info!("Preview matching riders with drivers");
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    let assignments = run_matching(conn, default, priority);
+
+    // Undo everything, even if matching failed part way
+    conn.execute("ROLLBACK;")?;
+    assignments
+}
+
+/// Match every event, applying each campus's assignments before the next
+/// so drivers serving several campuses are never overfilled
+fn run_matching(
+    conn: &Connection,
+    default: &dyn MatchStrategy,
+    priority: WaitlistPriority
+) -> Result<Vec<Assignment>, Box<dyn Error>> {
+    let mut all = Vec::new();
+    let now = Local::now().naive_local();
+
+    let campuses = get_campuses(conn)?;
+    let events = get_events(conn)?;
+    for event in events {
+        // Assignments are locked in
+        if event.is_frozen(now) {
+            continue;
+        }
+
+        let event_strategy = event.strategy.as_deref().and_then(get_strategy);
+        let strategy = event_strategy.as_deref().unwrap_or(default);
+
+        // Each leg is matched on its own
+        for leg in [Leg::To, Leg::Return] {
+            for campus in &campuses {
+                let mut rides = unassigned_campus_riders(conn, event.id, leg, &campus.code)?;
+                let drivers = get_available_drivers(conn, event.id, leg, &campus.code)?;
+
+                priority.sort(&mut rides);
+                let rides = waitlist_head(rides, &drivers);
+
+                let assignments = strategy.assign(&rides, &drivers);
+                assign_rides(conn, &assignments)?;
+                all.extend(assignments);
+            }
+        }
+    }
+
+    Ok(all)
+}
+
+/// Find new drivers right away for riders whose driver dropped out of an event.
+/// They are matched before anyone else waiting for a seat.
+/// Returns the riders that are still without a driver
+pub fn reassign_cancelled_rides(
+    conn: &Connection,
+    event_id: Uuid,
+    default: &dyn MatchStrategy,
+    priority: WaitlistPriority
+) -> Result<Vec<Ride>, Box<dyn Error>> {
+    info!("Reassign riders whose driver cancelled");
+    let event = match get_event(conn, event_id)? {
+        Some(event) => event,
+        None => return Ok(Vec::new())
+    };
+
+    let event_strategy = event.strategy.as_deref().and_then(get_strategy);
+    let strategy = event_strategy.as_deref().unwrap_or(default);
+
+    let campuses = get_campuses(conn)?;
+    let mut stranded = Vec::new();
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    for leg in [Leg::To, Leg::Return] {
+        for campus in &campuses {
+            let mut rides = cancelled_campus_riders(conn, event_id, leg, &campus.code)?;
+            let drivers = get_available_drivers(conn, event_id, leg, &campus.code)?;
+
+            priority.sort(&mut rides);
+            let assignments = strategy.assign(&rides, &drivers);
+            assign_rides(conn, &assignments)?;
+
+            stranded.extend(rides.into_iter().filter(|ride| {
+                !assignments.iter().any(|a| a.rider_id == ride.rider_id)
+            }));
+        }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(stranded)
+}
+
+/// Apply a list of assignments of riders to drivers
+fn assign_rides(conn: &Connection, assignments: &[Assignment]) -> Result<(), Box<dyn Error>> {
+    info!("Assign {} rides", assignments.len());
+    let mut assign_rider = conn.prepare(
+        include_str!("./sql/assign_rider.sql")
+    )?;
+
+    for assignment in assignments {
+        let driver_id = assignment.driver_id.to_string();
+        let rider_id = assignment.rider_id.to_string();
+        let event_id = assignment.event_id.to_string();
+        let leg: &str = assignment.leg.into();
+
+        assign_rider.reset()?;
+        assign_rider.bind(1, driver_id.as_str())?;
+        assign_rider.bind(2, event_id.as_str())?;
+        assign_rider.bind(3, rider_id.as_str())?;
+        assign_rider.bind(4, leg)?;
+
+        loop {
+            let state = assign_rider.next()?;
+            if state==State::Done { break; }
+        }
+    }
+
+    Ok(())
+}
+
+/// Get every ride for an event, for leadership to review
+pub fn get_event_rides(conn: &Connection, event_id: Uuid) -> Result<Vec<Ride>, Box<dyn Error>> {
+    info!("Get rides for event");
+    let mut cursor = conn.prepare(include_str!("./sql/get_event_rides.sql"))?.into_cursor();
+    cursor.bind(&[Value::String(event_id.to_string())])?;
+
+    let mut rides = Vec::new();
+
+    while let Some(row) = cursor.next()? {
+        rides.push(row.into());
+    }
+
+    Ok(rides)
+}
+
+/// Get every driver for an event along with how many seats are taken
+pub fn get_event_drivers(conn: &Connection, event_id: Uuid) -> Result<Vec<(Driver, i64)>, Box<dyn Error>> {
+    info!("Get drivers for event");
+    let mut cursor = conn.prepare(include_str!("./sql/get_event_drivers.sql"))?.into_cursor();
+    cursor.bind(&[Value::String(event_id.to_string())])?;
+
+    let mut drivers = Vec::new();
+
+    while let Some(row) = cursor.next()? {
+        drivers.push((row.into(), row[6].as_integer().unwrap()));
+    }
+
+    Ok(drivers)
+}
+
+/// Manually assign or unassign a rider for one leg of an event.
+/// The change is recorded along with the user who made it
+pub fn set_assignment(
+    conn: &Connection,
+    event_id: Uuid,
+    rider_id: Uuid,
+    leg: Leg,
+    driver_id: Option<Uuid>,
+    pinned: bool,
+    changed_by: Uuid
+) -> Result<(), Box<dyn Error>> {
+    info!("Set assignment for rider: {rider_id}");
+    let mut update = conn.prepare(include_str!("./sql/set_assignment.sql"))?;
+    let mut record = conn.prepare(include_str!("./sql/create_assignment_change.sql"))?;
+
+    let event_id = event_id.to_string();
+    let rider_id = rider_id.to_string();
+    let driver_id = driver_id.map(|id| id.to_string());
+    let leg: &str = leg.into();
+
+    update.bind(1, driver_id.as_deref())?;
+    update.bind(2, pinned as i64)?;
+    update.bind(3, event_id.as_str())?;
+    update.bind(4, rider_id.as_str())?;
+    update.bind(5, leg)?;
+
+    record.bind(1, event_id.as_str())?;
+    record.bind(2, rider_id.as_str())?;
+    record.bind(3, leg)?;
+    record.bind(4, driver_id.as_deref())?;
+    record.bind(5, pinned as i64)?;
+    record.bind(6, changed_by.to_string().as_str())?;
+    record.bind(7, Local::now().naive_local().timestamp())?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    loop {
+        let state = update.next()?;
+        if state==State::Done { break; }
+    }
+
+    loop {
+        let state = record.next()?;
+        if state==State::Done { break; }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(())
+}
+
+/// Get the manual assignment changes made for an event, newest first
+pub fn get_assignment_changes(conn: &Connection, event_id: Uuid) -> Result<Vec<AssignmentChange>, Box<dyn Error>> {
+    info!("Get assignment changes for event");
+    let mut cursor = conn.prepare(include_str!("./sql/get_assignment_changes.sql"))?.into_cursor();
+    cursor.bind(&[Value::String(event_id.to_string())])?;
+
+    let mut changes = Vec::new();
+
+    while let Some(row) = cursor.next()? {
+        changes.push(row.into());
+    }
+
+    Ok(changes)
+}
+
+/// Get list of unassigned riders for one leg of an event on a campus
+fn unassigned_campus_riders(conn: &Connection, event_id: Uuid, leg: Leg, campus: &CampusCode) -> Result<Vec<Ride>, Box<dyn Error>> {
+    let mut cursor = conn.prepare(include_str!("./sql/get_unassigned_riders.sql"))?.into_cursor();
+    let leg: &'static str = leg.into();
+    cursor.bind(&[
+        Value::String(event_id.to_string()),
+        Value::String(leg.into()),
+        Value::String(campus.to_string())
+    ])?;
+
+    let mut rides = Vec::new();
+
+    while let Some(row) = cursor.next()? {
+        rides.push(row.into());
+    }
+
+    Ok(rides)
+}
+
+/// Get list of riders on a campus whose driver dropped out of one leg of an event
+fn cancelled_campus_riders(conn: &Connection, event_id: Uuid, leg: Leg, campus: &CampusCode) -> Result<Vec<Ride>, Box<dyn Error>> {
+    let mut cursor = conn.prepare(include_str!("./sql/get_cancelled_riders.sql"))?.into_cursor();
+    let leg: &'static str = leg.into();
+    cursor.bind(&[
+        Value::String(event_id.to_string()),
+        Value::String(leg.into()),
+        Value::String(campus.to_string())
+    ])?;
+
+    let mut rides = Vec::new();
+
+    while let Some(row) = cursor.next()? {
+        rides.push(row.into());
+    }
+
+    Ok(rides)
+}
+
+/// Get who is riding with who for every upcoming event
+pub fn get_events_info(conn: &Connection) -> Result<Vec<EventInfo>, Box<dyn Error>> {
+    info!("Get current events info");
+    let now = Local::now().naive_local();
+    let mut events = Vec::new();
+
+    for event in get_events(conn)?.into_iter().filter(|event| event.time >= now) {
+        let mut cars = Vec::new();
+        for (driver, _) in get_event_drivers(conn, event.id)? {
+            // Skip drivers whose account or vehicle is gone
+            let user = get_user(conn, driver.driver_id)?;
+            let vehicle = get_vehicle(conn, driver.vehicle_id)?;
+            let (user, vehicle) = match (user, vehicle) {
+                (Some(user), Some(vehicle)) => (user, vehicle),
+                _ => continue
+            };
+
+            cars.push(CarInfo {
+                passengers: get_driver_passengers(conn, event.id, driver.driver_id, driver.leg)?,
+                driver: user,
+                vehicle,
+                leg: driver.leg,
+                campuses: driver.campuses,
+                seats: driver.seats,
+            });
+        }
+
+        let mut unassigned = Vec::new();
+        for ride in get_event_rides(conn, event.id)? {
+            if ride.driver_id.is_some() {
+                continue;
+            }
+
+            if let Some(user) = get_user(conn, ride.rider_id)? {
+                unassigned.push((user, ride));
+            }
+        }
+
+        events.push(EventInfo {
+            event,
+            cars,
+            unassigned,
+        });
+    }
+
+    Ok(events)
+}
+
+/// Create a new password reset request,
+/// replacing any other reset links the user was sent
+pub fn create_reset_request(conn: &Connection, user_id: Uuid) -> Result<Uuid, Box<dyn Error>> {
+    info!("Create password reset request");
+    let id = Uuid::new_v4();
+    let id_s = id.to_string();
+
+    let mut remove = conn.prepare(include_str!("./sql/delete_user_resets.sql"))?;
+    let mut stmt = conn.prepare(include_str!("./sql/create_reset.sql"))?;
+    let now = Local::now().naive_local().timestamp();
+
+    remove.bind(1, &*user_id.to_string())?;
+
+    stmt.bind(1, &*user_id.to_string())?;
+    stmt.bind(2, id_s.as_str())?;
+    stmt.bind(3, now)?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    loop {
+        let state = remove.next()?;
+        if state == State::Done { break; }
+    }
+
+    loop {
+        let state = stmt.next()?;
+        if state == State::Done { break; }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    Ok(id)
+}
+
+/// Get a reset request by id
+pub fn get_reset_request(conn: &Connection, id: Uuid) -> Result<Option<ResetRequest>, Box<dyn Error>> {
+    info!("Get reset request");
+
     let mut cursor = conn.prepare(
-        include_str!("./sql/get_driver_events.sql")
+        include_str!("./sql/get_request.sql")
     )?.into_cursor();
-    cursor.bind(&[Value::String(driver_id.to_string())])?;
 
-    let mut events = vec![];
+    cursor.bind(&[Value::String(id.to_string())])?;
+    let row = cursor.next()?;
+
+    if row.is_none() { return Ok(None); }
+    let row = row.unwrap();
+
+    Ok(Some(row.into()))
+}
+
+/// Use up a reset request along with every other reset link the user was sent.
+/// Returns the request if it was still there and hadn't expired
+pub fn redeem_reset_request(conn: &Connection, id: Uuid) -> Result<Option<ResetRequest>, Box<dyn Error>> {
+    info!("Redeem reset request");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_request.sql")
+    )?.into_cursor();
+    let mut remove = conn.prepare(include_str!("./sql/delete_user_resets.sql"))?;
+
+    cursor.bind(&[Value::String(id.to_string())])?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    let request: Option<ResetRequest> = cursor.next()?.map(|row| row.into());
+    drop(cursor);
+
+    if let Some(request) = &request {
+        remove.bind(1, &*request.user_id.to_string())?;
+
+        loop {
+            let state = remove.next()?;
+            if state == State::Done { break; }
+        }
+    }
+
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    let now = Local::now().naive_local();
+    Ok(request.filter(|request| !request.is_expired(now)))
+}
+
+/// Delete a reset request by id
+pub fn delete_reset_request(conn: &Connection, id: Uuid) -> Result<(), Box<dyn Error>> {
+    info!("Delete reset request");
+    let mut remove_reset = conn.prepare(
+        include_str!("./sql/delete_reset.sql")
+    )?;
+
+    remove_reset.bind(1, &*id.to_string())?;
 
-    while let Some(row) = cursor.next()? {
-        events.push(row.into());
+    loop {
+        let state = remove_reset.next()?;
+        if state==State::Done { break; }
     }
 
-    Ok(events)
+    Ok(())
 }
 
-/// Get all events a user is getting a ride
-pub fn get_rider_events(conn: &Connection, rider_id: Uuid) -> Result<Vec<Event>, Box<dyn Error>> {
-    info!("get rider events");
-    let mut cursor = conn.prepare(
-        include_str!("./sql/get_rider_events.sql")
-    )?.into_cursor();
-    cursor.bind(&[Value::String(rider_id.to_string())])?;
+/// Remove reset links that can no longer be used
+pub fn delete_expired_resets(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    info!("Delete expired reset requests");
+    let mut stmt = conn.prepare(include_str!("./sql/delete_expired_resets.sql"))?;
 
-    let mut events = vec![];
+    let expire_time = (Local::now().naive_local() - ResetRequest::lifetime()).timestamp();
+    stmt.bind(1, expire_time)?;
 
-    while let Some(row) = cursor.next()? {
-        events.push(row.into());
+    loop {
+        let state = stmt.next()?;
+        if state == State::Done { break; }
     }
 
-    Ok(events)
+    Ok(())
 }
 
-/// Delete a user from an event, whether they are a rider or a driver.
-/// Does not delete events for everyone, only removes a user from it
-pub fn delete_user_event(conn: &Connection, user_id: Uuid, event_id: Uuid) -> Result<(), Box<dyn Error>> {
-    info!("Removing user from event");
-
-    let mut remove_rides = conn.prepare(
-        include_str!("./sql/delete_user_rides.sql")
-    )?;
+/// Create a new login link for a user
+pub fn create_login_token(conn: &Connection, user_id: Uuid) -> Result<Uuid, Box<dyn Error>> {
+    info!("Create login token");
+    let id = Uuid::new_v4();
 
-    let mut remove_drives = conn.prepare(
-        include_str!("./sql/delete_user_rides_drives.sql")
-    )?;
+    let mut stmt = conn.prepare(include_str!("./sql/create_login_token.sql"))?;
+    let now = Local::now().naive_local().timestamp();
 
-    let mut remove_drivers = conn.prepare(
-        include_str!("./sql/delete_user_drives.sql")
-    )?;
+    stmt.bind(1, &*user_id.to_string())?;
+    stmt.bind(2, &*id.to_string())?;
+    stmt.bind(3, now)?;
 
-    let user_id = user_id.to_string();
-    let event_id = event_id.to_string();
+    loop {
+        let state = stmt.next()?;
+        if state == State::Done { break; }
+    }
 
-    remove_rides.bind(1, user_id.as_str())?;
-    remove_rides.bind(2, event_id.as_str())?;
+    Ok(id)
+}
 
-    remove_drives.bind(1, user_id.as_str())?;
-    remove_drives.bind(2, event_id.as_str())?;
+/// Use up a login link, returning the user it logs in.
+/// Links only work once, and not at all once they have expired
+pub fn redeem_login_token(conn: &Connection, id: Uuid) -> Result<Option<Uuid>, Box<dyn Error>> {
+    info!("Redeem login token");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_login_token.sql")
+    )?.into_cursor();
+    let mut remove = conn.prepare(include_str!("./sql/delete_login_token.sql"))?;
 
-    remove_drivers.bind(1, user_id.as_str())?;
-    remove_drivers.bind(2, event_id.as_str())?;
+    cursor.bind(&[Value::String(id.to_string())])?;
+    remove.bind(1, &*id.to_string())?;
 
     // Begin Transaction
     conn.execute("BEGIN;")?;
 
-    // Remove Rides
+    let token: Option<LoginToken> = cursor.next()?.map(|row| row.into());
+
     loop {
-        let state = remove_rides.next()?;
-        if state==State::Done { break; }
+        let state = remove.next()?;
+        if state == State::Done { break; }
     }
 
-    // Remove Drives
+    // End Transaction
+    conn.execute("COMMIT;")?;
+
+    let now = Local::now().naive_local();
+    Ok(token.filter(|token| !token.is_expired(now)).map(|token| token.user_id))
+}
+
+/// Remove login links that can no longer be used
+pub fn delete_expired_login_tokens(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    info!("Delete expired login tokens");
+    let mut stmt = conn.prepare(include_str!("./sql/delete_expired_login_tokens.sql"))?;
+
+    let expire_time = (Local::now().naive_local() - LoginToken::lifetime()).timestamp();
+    stmt.bind(1, expire_time)?;
+
     loop {
-        let state = remove_drives.next()?;
-        if state==State::Done { break; }
+        let state = stmt.next()?;
+        if state == State::Done { break; }
     }
 
-    // Remove drivers
+    Ok(())
+}
+
+/// Create a link that verifies a user's email, returns the token to send them
+pub fn create_verification(conn: &Connection, user_id: Uuid) -> Result<Uuid, Box<dyn Error>> {
+    info!("Create email verification");
+    let token_id = Uuid::new_v4();
+
+    let mut stmt = conn.prepare(include_str!("./sql/create_verification.sql"))?;
+
+    stmt.bind(1, &*user_id.to_string())?;
+    stmt.bind(2, &*token_id.to_string())?;
+    stmt.bind(3, Local::now().naive_local().timestamp())?;
+
     loop {
-        let state = remove_drivers.next()?;
+        let state = stmt.next()?;
         if state == State::Done { break; }
     }
 
-    // End Transaction
-    conn.execute("COMMIT;")?;
+    Ok(token_id)
+}
 
-    Ok(())
+/// Mark the user a verification link was sent to as verified,
+/// returns who was verified if the link was valid and hadn't expired
+pub fn verify_email(conn: &Connection, token_id: Uuid) -> Result<Option<Uuid>, Box<dyn Error>> {
+    info!("Verify email");
+    let mut cursor = conn.prepare(
+        include_str!("./sql/get_verification.sql")
+    )?.into_cursor();
+
+    cursor.bind(&[Value::String(token_id.to_string())])?;
+
+    let now = Local::now().naive_local();
+    let user_id = cursor.next()?
+        .map(Verification::from)
+        .filter(|verification| !verification.is_expired(now))
+        .map(|verification| verification.user_id);
+    drop(cursor);
+
+    if let Some(user_id) = user_id {
+        set_verified(conn, user_id)?;
+    }
+
+    Ok(user_id)
 }
 
-/// Get Driver information for an event
-fn get_event_driver(conn: &Connection, event_id: Uuid, user_id: Uuid) -> Result<Option<(User, Vehicle)>, Box<dyn Error>> {
-    info!("Get driver info for event");
-    let mut cursor = conn.prepare(include_str!("./sql/get_event_driver.sql"))?.into_cursor();
+/// Delete verification links that are too old to use
+pub fn delete_expired_verifications(conn: &Connection) -> Result<(), Box<dyn Error>> {
+    info!("Delete expired verifications");
+    let mut stmt = conn.prepare(include_str!("./sql/delete_expired_verifications.sql"))?;
 
-    cursor.bind(&[Value::String(event_id.to_string()), Value::String(user_id.to_string())])?;
-    let row = cursor.next()?;
+    let expire_time = (Local::now().naive_local() - Verification::lifetime()).timestamp();
+    stmt.bind(1, expire_time)?;
 
-    Ok(row.map(|row| {
-        (row.into(), row[5..].into())
-    }))
+    loop {
+        let state = stmt.next()?;
+        if state == State::Done { break; }
+    }
+
+    Ok(())
 }
 
-/// Get all information about an event for a given user
-pub fn get_events_data(conn: &Connection, user_id: Uuid) -> Result<Vec<EventData>, Box<dyn Error>> {
-    info!("Get event info for user");
-    let mut event_data = Vec::new();
+/// Mark a user's email as verified and forget their verification links
+pub fn set_verified(conn: &Connection, user_id: Uuid) -> Result<(), Box<dyn Error>> {
+    info!("Set user verified");
+    let mut verify = conn.prepare(include_str!("./sql/set_verified.sql"))?;
+    let mut remove = conn.prepare(include_str!("./sql/delete_user_verifications.sql"))?;
 
-    for event in get_rider_events(conn, user_id)? {
-        let driver = get_event_driver(conn, event.id, user_id)?;
-        event_data.push(EventData {
-            event,
-            driver,
-            riders: None,
-            is_driver: false
-        });
+    verify.bind(1, &*user_id.to_string())?;
+    remove.bind(1, &*user_id.to_string())?;
+
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
+
+    loop {
+        let state = verify.next()?;
+        if state == State::Done { break; }
     }
 
-    for event in get_driver_events(conn, user_id)? {
-        let riders = get_driver_passengers(conn, event.id, user_id)?;
-        event_data.push(EventData {
-            event,
-            riders: Some(riders),
-            driver: None,
-            is_driver: true
-        });
+    loop {
+        let state = remove.next()?;
+        if state == State::Done { break; }
     }
 
-    // Sort by event date
-    event_data.sort_by_cached_key(|ed| {
-        ed.event.time.timestamp()
-    });
+    // End Transaction
+    conn.execute("COMMIT;")?;
 
-    Ok(event_data)
+    Ok(())
 }
 
+/// Remember a device so the user stays logged in on it.
+/// Returns the device's id and the token that logs it in
+pub fn create_device(conn: &Connection, user_id: Uuid, name: &str) -> Result<(Uuid, Uuid), Box<dyn Error>> {
+    info!("Remembering device for user: {user_id}");
+    let id = Uuid::new_v4();
+    let token = Uuid::new_v4();
 
-/// Delete old events in the database
-pub fn delete_old_events(conn: &Connection) -> Result<(), Box<dyn Error>> {
-    info!("delete old events");
-    let mut remove_events = conn.prepare(
-        include_str!("./sql/delete_old_events.sql")
-    )?;
+    let mut stmt = conn.prepare(include_str!("./sql/create_device.sql"))?;
 
-    let expire_time = (Local::now() - chrono::Duration::days(1)).timestamp();
-    remove_events.bind(1, expire_time)?;
+    stmt.bind(1, &*id.to_string())?;
+    stmt.bind(2, &*user_id.to_string())?;
+    stmt.bind(3, &*token.to_string())?;
+    stmt.bind(4, name)?;
+    stmt.bind(5, Local::now().naive_local().timestamp())?;
 
     loop {
-        let state = remove_events.next()?;
-        if state==State::Done { break; }
+        let state = stmt.next()?;
+        if state == State::Done { break; }
     }
 
-    Ok(())
+    Ok((id, token))
 }
 
-/// Pair riders with rides
-pub fn match_rides(conn: &Connection) -> Result<(), Box<dyn Error>> {
-    info!("Match riders with drivers");
-    // Begin Transaction
-    conn.execute("BEGIN;")?;
+/// Get a remembered device by its id
+pub fn get_device(conn: &Connection, id: Uuid) -> Result<Option<Device>, Box<dyn Error>> {
+    info!("Get device: {id}");
+    let mut cursor = conn.prepare(include_str!("./sql/get_device.sql"))?.into_cursor();
+    cursor.bind(&[Value::String(id.to_string())])?;
 
-    let events = get_events(&conn)?;
-    for event in events {
-        // RIT
-        let rit_rides = unassigned_campus_riders(&conn, event.id, Campus::RIT)?;
-
-        'outer: for ride in rit_rides {
-            let mut driver_index = 0;
-            let rit_drivers = get_available_drivers(&conn, event.id, Campus::RIT)?;
-            if driver_index >= rit_drivers.len() {
-                break;
-            }
+    Ok(cursor.next()?.map(|row| row.into()))
+}
 
-            while rit_drivers[driver_index].0.seats - rit_drivers[driver_index].1 <= 0 {
-                driver_index += 1;
-                if driver_index >= rit_drivers.len() {
-                    break 'outer;
-                }
-            }
+/// Get the remembered device a token logs in, if it hasn't gone unused for too long
+pub fn get_device_by_token(conn: &Connection, token: Uuid) -> Result<Option<Device>, Box<dyn Error>> {
+    info!("Get device by token");
+    let mut cursor = conn.prepare(include_str!("./sql/get_device_by_token.sql"))?.into_cursor();
+    cursor.bind(&[Value::String(token.to_string())])?;
 
-            assign_ride(&conn, event.id, ride.rider_id, rit_drivers[driver_index].0.driver_id)?;
-        }
+    let device: Option<Device> = cursor.next()?.map(|row| row.into());
+    let now = Local::now().naive_local();
 
-        // UofR
-        let ur_rides = unassigned_campus_riders(&conn, event.id, Campus::UofR)?;
+    Ok(device.filter(|device| now - device.last_seen < Device::lifetime()))
+}
 
-        'outer: for ride in ur_rides {
-            let mut driver_index = 0;
-            let ur_drivers = get_available_drivers(&conn, event.id, Campus::UofR)?;
-            if driver_index >= ur_drivers.len() {
-                break;
-            }
+/// Get every device a user is remembered on, most recently used first
+pub fn get_user_devices(conn: &Connection, user_id: Uuid) -> Result<Vec<Device>, Box<dyn Error>> {
+    info!("Get devices for user: {user_id}");
+    let mut cursor = conn.prepare(include_str!("./sql/get_user_devices.sql"))?.into_cursor();
+    cursor.bind(&[Value::String(user_id.to_string())])?;
 
-            while ur_drivers[driver_index].0.seats - ur_drivers[driver_index].1 <= 0 {
-                driver_index += 1;
-                if driver_index >= ur_drivers.len() {
-                    break 'outer;
-                }
-            }
-            println!("Assign???");
+    let mut devices = Vec::new();
+    while let Some(row) = cursor.next()? {
+        devices.push(row.into());
+    }
 
-            assign_ride(&conn, event.id, ride.rider_id, ur_drivers[driver_index].0.driver_id)?;
-        }
+    Ok(devices)
+}
+
+/// Record that a device was just used
+pub fn touch_device(conn: &Connection, id: Uuid) -> Result<(), Box<dyn Error>> {
+    let mut stmt = conn.prepare(include_str!("./sql/touch_device.sql"))?;
+
+    stmt.bind(1, Local::now().naive_local().timestamp())?;
+    stmt.bind(2, &*id.to_string())?;
+
+    loop {
+        let state = stmt.next()?;
+        if state == State::Done { break; }
     }
 
-    // End Transaction
-    conn.execute("COMMIT;")?;
     Ok(())
 }
 
-/// Assign rider to driver for an event
-fn assign_ride(conn: &Connection, event_id: Uuid, rider_id: Uuid, driver_id: Uuid) -> Result<(), Box<dyn Error>> {
-    info!("Assign a ride");
-    let mut assign_rider = conn.prepare(
-        include_str!("./sql/assign_rider.sql")
-    )?;
-
-    let driver_id = driver_id.to_string();
-    let rider_id = rider_id.to_string();
-    let event_id = event_id.to_string();
+/// Forget one of a user's devices, logging it out
+pub fn delete_device(conn: &Connection, user_id: Uuid, id: Uuid) -> Result<(), Box<dyn Error>> {
+    info!("Revoking device: {id}");
+    let mut stmt = conn.prepare(include_str!("./sql/delete_device.sql"))?;
 
-    assign_rider.bind(1, driver_id.as_str())?;
-    assign_rider.bind(2, event_id.as_str())?;
-    assign_rider.bind(3, rider_id.as_str())?;
+    stmt.bind(1, &*id.to_string())?;
+    stmt.bind(2, &*user_id.to_string())?;
 
     loop {
-        let state = assign_rider.next()?;
-        if state==State::Done { break; }
+        let state = stmt.next()?;
+        if state == State::Done { break; }
     }
 
     Ok(())
 }
 
-/// Get list of unassigned riders for an event on a campus
-fn unassigned_campus_riders(conn: &Connection, event_id: Uuid, campus: Campus) -> Result<Vec<Ride>, Box<dyn Error>> {
-    let mut cursor = conn.prepare(include_str!("./sql/get_unassigned_riders.sql"))?.into_cursor();
-    cursor.bind(&[Value::String(event_id.to_string()), Value::String(campus.into())])?;
+/// Create an invite link. Creating an invite with an id that already exists does nothing
+pub fn create_invite(
+    conn: &Connection,
+    id: Uuid,
+    created_by: Option<Uuid>,
+    email: Option<String>,
+    expires_at: Option<NaiveDateTime>,
+    max_uses: Option<i64>
+) -> Result<(), Box<dyn Error>> {
+    info!("Create invite: {id}");
+    let mut stmt = conn.prepare(include_str!("./sql/create_invite.sql"))?;
 
-    let mut rides = Vec::new();
+    stmt.bind(1, &*id.to_string())?;
+    stmt.bind(2, created_by.map(|id| id.to_string()).as_deref())?;
+    stmt.bind(3, email.as_deref())?;
+    stmt.bind(4, Local::now().naive_local().timestamp())?;
+    stmt.bind(5, expires_at.map(|time| time.timestamp()))?;
+    stmt.bind(6, max_uses)?;
 
-    while let Some(row) = cursor.next()? {
-        rides.push(row.into());
+    loop {
+        let state = stmt.next()?;
+        if state == State::Done { break; }
     }
 
-    Ok(rides)
+    Ok(())
 }
 
-/// Get info about current events
-pub fn get_events_info(conn: &Connection) -> Result<Vec<EventInfo>, Box<dyn Error>> {
-   info!("Get current events info");
-   let mut events = Vec::new();
+/// Get an invite by its id
+pub fn get_invite(conn: &Connection, id: Uuid) -> Result<Option<Invite>, Box<dyn Error>> {
+    info!("Get invite: {id}");
+    let mut cursor = conn.prepare(include_str!("./sql/get_invite.sql"))?.into_cursor();
+    cursor.bind(&[Value::String(id.to_string())])?;
+
+    Ok(cursor.next()?.map(|row| row.into()))
+}
+
+/// Get every invite, newest first
+pub fn get_invites(conn: &Connection) -> Result<Vec<Invite>, Box<dyn Error>> {
+    info!("Get invites");
+    let mut cursor = conn.prepare(include_str!("./sql/get_invites.sql"))?.into_cursor();
 
-    let mut cursor = conn.prepare(include_str!("./sql/events_info.sql"))?.into_cursor();
+    let mut invites = Vec::new();
     while let Some(row) = cursor.next()? {
-        events.push(row.into());
+        invites.push(row.into());
     }
 
-   Ok(events)
+    Ok(invites)
 }
 
-/// Create a new password reset request
-pub fn create_reset_request(conn: &Connection, user_id: Uuid) -> Result<Uuid, Box<dyn Error>> {
-    info!("Create password reset request");
-    let id = Uuid::new_v4();
-    let id_s = id.to_string();
-
-    let mut stmt = conn.prepare(include_str!("./sql/create_reset.sql"))?;
-    let now = Local::now().naive_local().timestamp();
-
-    stmt.bind(1, &*user_id.to_string())?;
-    stmt.bind(2, id_s.as_str())?;
-    stmt.bind(3, now)?;
+/// Stop an invite from being used again
+pub fn revoke_invite(conn: &Connection, id: Uuid) -> Result<(), Box<dyn Error>> {
+    info!("Revoke invite: {id}");
+    let mut stmt = conn.prepare(include_str!("./sql/revoke_invite.sql"))?;
+    stmt.bind(1, &*id.to_string())?;
 
     loop {
         let state = stmt.next()?;
         if state == State::Done { break; }
     }
 
-    Ok(id)
+    Ok(())
 }
 
-/// Get a reset request by id
-pub fn get_reset_request(conn: &Connection, id: Uuid) -> Result<Option<ResetRequest>, Box<dyn Error>> {
-    info!("Get reset request");
+/// Create a new user who signed up with an invite, counting the use and
+/// recording who invited them in the same transaction.
+/// Returns the new user's id, or None if the invite can no longer be used
+pub fn create_invited_user(
+    conn: &Connection,
+    invite_id: Uuid,
+    email: String,
+    fullname: String,
+    password: String,
+    number: String
+) -> Result<Option<Uuid>, Box<dyn Error>> {
+    info!("Use invite: {invite_id}");
+    // Begin Transaction
+    conn.execute("BEGIN;")?;
 
-    let mut cursor = conn.prepare(
-        include_str!("./sql/get_request.sql")
-    )?.into_cursor();
+    let created = add_invited_user(conn, invite_id, email, fullname, password, number);
 
-    cursor.bind(&[Value::String(id.to_string())])?;
-    let row = cursor.next()?;
+    // End Transaction, keeping nothing unless the user was created
+    match created {
+        Ok(Some(_)) => conn.execute("COMMIT;")?,
+        _ => conn.execute("ROLLBACK;")?
+    }
+    created
+}
 
-    if row.is_none() { return Ok(None); }
-    let row = row.unwrap();
+/// Count a use of an invite then add the user it was used by
+fn add_invited_user(
+    conn: &Connection,
+    invite_id: Uuid,
+    email: String,
+    fullname: String,
+    password: String,
+    number: String
+) -> Result<Option<Uuid>, Box<dyn Error>> {
+    let mut count = conn.prepare(include_str!("./sql/use_invite.sql"))?;
+    count.bind(1, &*invite_id.to_string())?;
+    count.bind(2, Local::now().naive_local().timestamp())?;
 
-    Ok(Some(row.into()))
-}
+    loop {
+        let state = count.next()?;
+        if state == State::Done { break; }
+    }
 
-/// Delete a reset request by id
-pub fn delete_reset_request(conn: &Connection, id: Uuid) -> Result<(), Box<dyn Error>> {
-    info!("delete old events");
-    let mut remove_reset = conn.prepare(
-        include_str!("./sql/delete_reset.sql")
-    )?;
+    // The invite was revoked, expired or used up since it was checked
+    if conn.change_count() == 0 {
+        return Ok(None);
+    }
 
-    remove_reset.bind(1, &*id.to_string())?;
+    let user_id = insert_user(conn, email, fullname, password, number)?;
+
+    let mut record = conn.prepare(include_str!("./sql/set_user_invite.sql"))?;
+    record.bind(1, &*invite_id.to_string())?;
+    record.bind(2, &*user_id.to_string())?;
 
     loop {
-        let state = remove_reset.next()?;
-        if state==State::Done { break; }
+        let state = record.next()?;
+        if state == State::Done { break; }
     }
 
-    Ok(())
+    Ok(Some(user_id))
 }
@@ -1,9 +1,12 @@
 use reqwest::Client;
 use std::error::Error;
+use tokio::sync::mpsc::UnboundedReceiver;
 
-const URL: &'static str = "https://api.mailgun.net/v3/rides.vstelt.dev/messages";
-const FROM: &'static str = "ACF Rides <mail@rides.vstelt.dev>";
-const TEMPLATE: &'static str = "rides_reset_pw";
+const URL: &str = "https://api.mailgun.net/v3/rides.vstelt.dev/messages";
+const FROM: &str = "ACF Rides <mail@rides.vstelt.dev>";
+const TEMPLATE: &str = "rides_reset_pw";
+const LOGIN_TEMPLATE: &str = "rides_login_link";
+const VERIFY_TEMPLATE: &str = "rides_verify_email";
 
 pub async fn send_reset_email(to: &str, reset_id: &str) -> Result<(), Box<dyn Error>> {
     let key = std::env::var("MAILGUN_KEY")?;
@@ -25,3 +28,148 @@ pub async fn send_reset_email(to: &str, reset_id: &str) -> Result<(), Box<dyn Er
 
     Ok(())
 }
+
+/// Send a link that logs the user in without their password
+pub async fn send_login_email(to: &str, token_id: &str) -> Result<(), Box<dyn Error>> {
+    let key = std::env::var("MAILGUN_KEY")?;
+
+    let params = [
+        ("from", FROM),
+        ("to", to),
+        ("template", LOGIN_TEMPLATE),
+        ("subject", "Your Login Link"),
+        ("t:variables", &format!("{{\"token_id\": \"{}\"}}", token_id))
+    ];
+
+    let client = Client::new();
+
+    client.post(URL)
+        .basic_auth("api", Some(key))
+        .form(&params)
+        .send().await?;
+
+    Ok(())
+}
+
+/// Send a link that confirms the user owns their email
+pub async fn send_verification_email(to: &str, token_id: &str) -> Result<(), Box<dyn Error>> {
+    let key = std::env::var("MAILGUN_KEY")?;
+
+    let params = [
+        ("from", FROM),
+        ("to", to),
+        ("template", VERIFY_TEMPLATE),
+        ("subject", "Verify Your Email"),
+        ("t:variables", &format!("{{\"token_id\": \"{}\"}}", token_id))
+    ];
+
+    let client = Client::new();
+
+    client.post(URL)
+        .basic_auth("api", Some(key))
+        .form(&params)
+        .send().await?;
+
+    Ok(())
+}
+
+/// Let leadership know that riders were left without a seat after their driver
+/// dropped out or gave up seats
+pub async fn send_leadership_alert(event_name: &str, riders: &[String]) -> Result<(), Box<dyn Error>> {
+    let key = std::env::var("MAILGUN_KEY")?;
+    let to = std::env::var("LEADERSHIP_EMAIL")?;
+
+    let text = format!(
+        "Riders lost their seat for {} and there are no open seats left for:\n\n{}",
+        event_name,
+        riders.join("\n")
+    );
+
+    let params = [
+        ("from", FROM),
+        ("to", &to),
+        ("subject", "Riders Need a Driver"),
+        ("text", &text)
+    ];
+
+    let client = Client::new();
+
+    client.post(URL)
+        .basic_auth("api", Some(key))
+        .form(&params)
+        .send().await?;
+
+    Ok(())
+}
+
+/// Let a rider or driver know that an event they're attending has changed
+pub async fn send_event_update(to: &str, event_name: &str, changes: &[String]) -> Result<(), Box<dyn Error>> {
+    let key = std::env::var("MAILGUN_KEY")?;
+
+    let text = format!(
+        "{} has been updated:\n\n{}\n\nCheck your upcoming rides for the latest details.",
+        event_name,
+        changes.join("\n")
+    );
+    let subject = format!("{} Has Changed", event_name);
+
+    let params = [
+        ("from", FROM),
+        ("to", to),
+        ("subject", &subject),
+        ("text", &text)
+    ];
+
+    let client = Client::new();
+
+    client.post(URL)
+        .basic_auth("api", Some(key))
+        .form(&params)
+        .send().await?;
+
+    Ok(())
+}
+
+/// An email to send one rider or driver about changes to an event
+#[derive(Debug)]
+pub struct EventUpdate {
+    pub to: String,
+    pub event_name: String,
+    pub changes: Vec<String>
+}
+
+/// Send event updates as they are queued, so a big event doesn't hold up
+/// the organizer who changed it
+pub async fn send_event_updates(mut rx: UnboundedReceiver<EventUpdate>) {
+    while let Some(update) = rx.recv().await {
+        send_event_update(&update.to, &update.event_name, &update.changes).await.ok();
+    }
+}
+
+/// Let a rider or driver know that an event they signed up for was called off
+pub async fn send_event_cancelled(to: &str, event_name: &str, time: &str) -> Result<(), Box<dyn Error>> {
+    let key = std::env::var("MAILGUN_KEY")?;
+
+    let text = format!(
+        "{} on {} has been cancelled. Your ride or drive for it has been removed.",
+        event_name,
+        time
+    );
+    let subject = format!("{} Is Cancelled", event_name);
+
+    let params = [
+        ("from", FROM),
+        ("to", to),
+        ("subject", &subject),
+        ("text", &text)
+    ];
+
+    let client = Client::new();
+
+    client.post(URL)
+        .basic_auth("api", Some(key))
+        .form(&params)
+        .send().await?;
+
+    Ok(())
+}
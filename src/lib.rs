@@ -3,3 +3,11 @@ pub mod models;
 pub mod webserver;
 pub mod worker;
 pub mod email;
+pub mod matching;
+
+// The route tests share the integration tests' fixtures, which name this crate `rides`
+#[cfg(test)]
+extern crate self as rides;
+#[cfg(test)]
+#[path = "../tests/common/mod.rs"]
+mod fixtures;
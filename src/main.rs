@@ -1,5 +1,4 @@
 use rides::{db, webserver, worker};
-use actix_web;
 
 use std::sync::mpsc;
 
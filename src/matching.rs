@@ -0,0 +1,487 @@
+use std::cmp::Reverse;
+use std::env;
+
+use uuid::Uuid;
+
+use crate::models::{Driver, Leg, Ride};
+
+/// A rider placed into a driver's car for one leg of an event
+pub struct Assignment {
+    pub event_id: Uuid,
+    pub rider_id: Uuid,
+    pub driver_id: Uuid,
+    pub leg: Leg,
+}
+
+/// A method of pairing unassigned riders with drivers.
+/// Strategies only propose assignments, `db::match_rides` applies them
+pub trait MatchStrategy {
+    /// Assign riders to drivers.
+    /// Each driver comes with the number of seats that are already taken,
+    /// a driver must never be given more riders than they have open seats.
+    /// Riders in the same group must all share one car or all stay unassigned
+    fn assign(&self, rides: &[Ride], drivers: &[(Driver, i64)]) -> Vec<Assignment>;
+}
+
+/// Split rides into parties that must share a car, keeping the order of
+/// each party's first rider. Riders without a group are a party of one
+fn parties(rides: &[Ride]) -> Vec<Vec<&Ride>> {
+    let mut parties: Vec<Vec<&Ride>> = Vec::new();
+
+    for ride in rides {
+        let party = ride.group_id.and_then(|group_id| {
+            parties.iter_mut().find(|party| party[0].group_id == Some(group_id))
+        });
+
+        match party {
+            Some(party) => party.push(ride),
+            None => parties.push(vec![ride])
+        }
+    }
+
+    parties
+}
+
+/// Place every rider in a party into a driver's car
+fn seat(assignments: &mut Vec<Assignment>, party: &[&Ride], driver: &Driver) {
+    for ride in party {
+        assignments.push(Assignment {
+            event_id: ride.event_id,
+            rider_id: ride.rider_id,
+            driver_id: driver.driver_id,
+            leg: ride.leg
+        });
+    }
+}
+
+/// Fill the first driver's car before moving on to the next one
+pub struct Greedy;
+
+impl MatchStrategy for Greedy {
+    fn assign(&self, rides: &[Ride], drivers: &[(Driver, i64)]) -> Vec<Assignment> {
+        let mut open: Vec<(&Driver, i64)> = drivers.iter()
+            .map(|(driver, taken)| (driver, driver.seats - taken))
+            .collect();
+
+        let mut assignments = Vec::new();
+
+        for party in parties(rides) {
+            let size = party.len() as i64;
+
+            // First driver with room for the whole party
+            if let Some((driver, seats)) = open.iter_mut().find(|(_, seats)| *seats >= size) {
+                *seats -= size;
+                seat(&mut assignments, &party, driver);
+            }
+        }
+
+        assignments
+    }
+}
+
+/// Spread riders evenly across drivers, each party goes to the driver
+/// with the fewest passengers that still has room for them
+pub struct Balanced;
+
+impl MatchStrategy for Balanced {
+    fn assign(&self, rides: &[Ride], drivers: &[(Driver, i64)]) -> Vec<Assignment> {
+        let mut taken: Vec<(&Driver, i64)> = drivers.iter()
+            .map(|(driver, taken)| (driver, *taken))
+            .collect();
+
+        let mut assignments = Vec::new();
+
+        for party in parties(rides) {
+            let size = party.len() as i64;
+
+            // min_by_key keeps the first driver on ties, preserving driver order
+            let next = taken.iter_mut()
+                .filter(|(driver, taken)| driver.seats - *taken >= size)
+                .min_by_key(|(_, taken)| *taken);
+
+            if let Some((driver, taken)) = next {
+                *taken += size;
+                seat(&mut assignments, &party, driver);
+            }
+        }
+
+        assignments
+    }
+}
+
+/// Keep riders at the same or nearby pickup points in the same car.
+/// Riders are grouped by pickup area and each group goes to the smallest
+/// car it fits in, only spilling into another car when no car is big enough
+pub struct Clustered;
+
+impl MatchStrategy for Clustered {
+    fn assign(&self, rides: &[Ride], drivers: &[(Driver, i64)]) -> Vec<Assignment> {
+        // Group parties by the pickup area of their first rider,
+        // unknown locations form their own group
+        let mut clusters: Vec<(Option<&str>, Vec<Vec<&Ride>>)> = Vec::new();
+        for party in parties(rides) {
+            let area = party[0].pickup_area.as_deref();
+
+            match clusters.iter_mut().find(|(a, _)| *a == area) {
+                Some((_, cluster)) => cluster.push(party),
+                None => clusters.push((area, vec![party]))
+            }
+        }
+
+        // Keep riders at the same point next to each other so they share a car
+        // even if their area has to be split
+        for (_, cluster) in clusters.iter_mut() {
+            cluster.sort_by_key(|party| party[0].pickup_point.clone());
+        }
+
+        // Largest areas first, unknown locations last
+        clusters.sort_by_key(|(area, cluster)| {
+            let riders: usize = cluster.iter().map(Vec::len).sum();
+            (area.is_none(), Reverse(riders))
+        });
+
+        let mut open: Vec<(&Driver, i64)> = drivers.iter()
+            .map(|(driver, taken)| (driver, driver.seats - taken))
+            .collect();
+
+        let mut assignments = Vec::new();
+
+        for (_, mut cluster) in clusters {
+            while !cluster.is_empty() {
+                let remaining: i64 = cluster.iter().map(|party| party.len() as i64).sum();
+
+                // Smallest car that fits everyone left, otherwise the largest car.
+                // Both keep the first driver on ties
+                let fits = open.iter_mut()
+                    .filter(|(_, seats)| *seats >= remaining)
+                    .min_by_key(|(_, seats)| *seats);
+                let next = match fits {
+                    Some(next) => Some(next),
+                    None => open.iter_mut().rev()
+                        .filter(|(_, seats)| *seats > 0)
+                        .max_by_key(|(_, seats)| *seats)
+                };
+
+                let (driver, seats) = match next {
+                    Some(next) => next,
+                    None => break
+                };
+
+                let before = cluster.len();
+                cluster.retain(|party| {
+                    let size = party.len() as i64;
+                    if size > *seats {
+                        return true;
+                    }
+
+                    *seats -= size;
+                    seat(&mut assignments, party, driver);
+                    false
+                });
+
+                // Nothing fit in the largest car, so it won't fit anywhere else
+                if cluster.len() == before {
+                    break;
+                }
+            }
+        }
+
+        assignments
+    }
+}
+
+/// Order riders waiting for a seat are given one
+#[derive(Clone, Copy)]
+pub enum WaitlistPriority {
+    /// Earliest request first
+    FirstCome,
+    /// Riders who have never been given a ride first, then earliest request
+    FirstTime
+}
+
+impl WaitlistPriority {
+    /// Sort rides into waitlist order.
+    /// The sort is stable so riders who asked at the same time keep their order
+    pub fn sort(&self, rides: &mut [Ride]) {
+        match self {
+            WaitlistPriority::FirstCome => rides.sort_by_key(|ride| ride.request_time),
+            WaitlistPriority::FirstTime => rides.sort_by_key(|ride| (ride.rides_taken > 0, ride.request_time))
+        }
+    }
+}
+
+/// Get the waitlist priority set by the WAITLIST_PRIORITY environment variable.
+/// Defaults to first come if unset or unknown
+pub fn configured_priority() -> WaitlistPriority {
+    match env::var("WAITLIST_PRIORITY").as_deref() {
+        Ok("first_time") => WaitlistPriority::FirstTime,
+        _ => WaitlistPriority::FirstCome
+    }
+}
+
+/// Keep only the parties at the front of the waitlist that there are open seats for,
+/// so every strategy gives seats out in waitlist order when they run out.
+/// Parties never split, so one only counts as seated if it fits in a single car.
+/// A party too big for the seats left in any car is skipped for smaller parties behind it
+pub fn waitlist_head(rides: Vec<Ride>, drivers: &[(Driver, i64)]) -> Vec<Ride> {
+    let mut open: Vec<i64> = drivers.iter()
+        .map(|(driver, taken)| (driver.seats - taken).max(0))
+        .collect();
+
+    let mut seated = Vec::new();
+    for party in parties(&rides) {
+        let size = party.len() as i64;
+
+        // Tightest car that fits, leaving room in bigger cars for bigger parties
+        let car = open.iter_mut()
+            .filter(|seats| **seats >= size)
+            .min_by_key(|seats| **seats);
+
+        if let Some(seats) = car {
+            *seats -= size;
+            seated.extend(party.iter().map(|ride| ride.rider_id));
+        }
+    }
+
+    rides.into_iter()
+        .filter(|ride| seated.contains(&ride.rider_id))
+        .collect()
+}
+
+/// Pick which of a driver's passengers have to find another car when the
+/// driver now has fewer seats. Pinned riders keep their seats first, then
+/// riders who asked earliest. Parties are kept together
+pub fn overflow(rides: &[Ride], seats: i64) -> Vec<&Ride> {
+    let mut parties = parties(rides);
+    // Stable, so earlier requests stay ahead within pinned and unpinned riders
+    parties.sort_by_key(|party| !party.iter().any(|ride| ride.pinned));
+
+    let mut open = seats;
+    let mut bumped = Vec::new();
+    for party in parties {
+        let size = party.len() as i64;
+        if size <= open {
+            open -= size;
+        } else {
+            bumped.extend(party);
+        }
+    }
+
+    bumped
+}
+
+/// Get a strategy by its name
+pub fn get_strategy(name: &str) -> Option<Box<dyn MatchStrategy>> {
+    match name {
+        "greedy" => Some(Box::new(Greedy)),
+        "balanced" => Some(Box::new(Balanced)),
+        "clustered" => Some(Box::new(Clustered)),
+        _ => None
+    }
+}
+
+/// Get the strategy set by the MATCH_STRATEGY environment variable.
+/// Defaults to greedy if unset or unknown
+pub fn configured_strategy() -> Box<dyn MatchStrategy> {
+    env::var("MATCH_STRATEGY").ok()
+        .and_then(|name| get_strategy(&name))
+        .unwrap_or_else(|| Box::new(Greedy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    /// A ride on the main campus with no known pickup point, asked for in order
+    fn ride(request_time: i64) -> Ride {
+        Ride {
+            rider_id: Uuid::new_v4(),
+            driver_id: None,
+            event_id: Uuid::nil(),
+            campus: "MAIN".parse().unwrap(),
+            pickup_location: String::new(),
+            pickup_point: None,
+            pickup_area: None,
+            group_id: None,
+            driver_cancelled: false,
+            leg: Leg::To,
+            pinned: false,
+            request_time: NaiveDateTime::from_timestamp(request_time, 0),
+            rides_taken: 0
+        }
+    }
+
+    /// A ride from a known pickup point
+    fn ride_at(request_time: i64, point: &str, area: &str) -> Ride {
+        Ride {
+            pickup_point: Some(point.to_string()),
+            pickup_area: Some(area.to_string()),
+            ..ride(request_time)
+        }
+    }
+
+    /// Rides that must share a car
+    fn group(request_times: &[i64]) -> Vec<Ride> {
+        let group_id = Some(Uuid::new_v4());
+        request_times.iter()
+            .map(|&request_time| Ride { group_id, ..ride(request_time) })
+            .collect()
+    }
+
+    /// A driver with some of their seats already taken
+    fn driver(seats: i64, taken: i64) -> (Driver, i64) {
+        let driver = Driver {
+            event_id: Uuid::nil(),
+            driver_id: Uuid::new_v4(),
+            seats,
+            vehicle_id: Uuid::new_v4(),
+            campuses: vec!["MAIN".parse().unwrap()],
+            leg: Leg::To
+        };
+        (driver, taken)
+    }
+
+    /// The riders given to each driver, in driver order
+    fn cars(assignments: &[Assignment], drivers: &[(Driver, i64)]) -> Vec<Vec<Uuid>> {
+        drivers.iter()
+            .map(|(driver, _)| assignments.iter()
+                .filter(|assignment| assignment.driver_id == driver.driver_id)
+                .map(|assignment| assignment.rider_id)
+                .collect())
+            .collect()
+    }
+
+    #[test]
+    fn greedy_fills_the_first_car_first() {
+        let rides: Vec<Ride> = (0..3).map(ride).collect();
+        let drivers = vec![driver(2, 0), driver(2, 0)];
+
+        let assignments = Greedy.assign(&rides, &drivers);
+        assert_eq!(cars(&assignments, &drivers), vec![
+            vec![rides[0].rider_id, rides[1].rider_id],
+            vec![rides[2].rider_id]
+        ]);
+    }
+
+    #[test]
+    fn greedy_counts_seats_already_taken() {
+        let rides: Vec<Ride> = (0..4).map(ride).collect();
+        let drivers = vec![driver(3, 2), driver(2, 0)];
+
+        // The last rider is left without a seat
+        let assignments = Greedy.assign(&rides, &drivers);
+        assert_eq!(cars(&assignments, &drivers), vec![
+            vec![rides[0].rider_id],
+            vec![rides[1].rider_id, rides[2].rider_id]
+        ]);
+    }
+
+    #[test]
+    fn balanced_spreads_riders_across_cars() {
+        let rides: Vec<Ride> = (0..4).map(ride).collect();
+        let drivers = vec![driver(3, 1), driver(3, 0)];
+
+        // Each rider goes to the emptiest car, the first car wins ties
+        let assignments = Balanced.assign(&rides, &drivers);
+        assert_eq!(cars(&assignments, &drivers), vec![
+            vec![rides[1].rider_id, rides[3].rider_id],
+            vec![rides[0].rider_id, rides[2].rider_id]
+        ]);
+    }
+
+    #[test]
+    fn balanced_never_overfills_a_car() {
+        let rides: Vec<Ride> = (0..4).map(ride).collect();
+        let drivers = vec![driver(1, 0), driver(4, 2)];
+
+        let assignments = Balanced.assign(&rides, &drivers);
+        assert_eq!(cars(&assignments, &drivers), vec![
+            vec![rides[0].rider_id],
+            vec![rides[1].rider_id, rides[2].rider_id]
+        ]);
+    }
+
+    #[test]
+    fn clustered_keeps_pickup_areas_together() {
+        let rides = vec![
+            ride_at(0, "Library", "North"),
+            ride_at(1, "Gym", "South"),
+            ride_at(2, "Library", "North"),
+            ride_at(3, "Pool", "South"),
+            ride_at(4, "Gym", "South")
+        ];
+        let drivers = vec![driver(2, 0), driver(3, 0)];
+
+        // The bigger area takes the smallest car it fits in,
+        // riders at the same point sit next to each other
+        let assignments = Clustered.assign(&rides, &drivers);
+        assert_eq!(cars(&assignments, &drivers), vec![
+            vec![rides[0].rider_id, rides[2].rider_id],
+            vec![rides[1].rider_id, rides[4].rider_id, rides[3].rider_id]
+        ]);
+    }
+
+    #[test]
+    fn clustered_splits_an_area_only_when_no_car_fits_it() {
+        let rides = vec![
+            ride(0),
+            ride_at(1, "Pool", "South"),
+            ride_at(2, "Gym", "South"),
+            ride_at(3, "Gym", "South")
+        ];
+        let drivers = vec![driver(2, 0), driver(2, 0)];
+
+        // Riders with an unknown location take whatever seats are left
+        let assignments = Clustered.assign(&rides, &drivers);
+        assert_eq!(cars(&assignments, &drivers), vec![
+            vec![rides[2].rider_id, rides[3].rider_id],
+            vec![rides[1].rider_id, rides[0].rider_id]
+        ]);
+    }
+
+    #[test]
+    fn parties_keep_groups_together() {
+        let mut rides = group(&[0, 2]);
+        rides.insert(1, ride(1));
+
+        let parties: Vec<Vec<Uuid>> = parties(&rides).iter()
+            .map(|party| party.iter().map(|ride| ride.rider_id).collect())
+            .collect();
+        assert_eq!(parties, vec![
+            vec![rides[0].rider_id, rides[2].rider_id],
+            vec![rides[1].rider_id]
+        ]);
+    }
+
+    #[test]
+    fn groups_are_never_split_across_cars() {
+        let rides = group(&[0, 1]);
+        let drivers = vec![driver(1, 0), driver(3, 1)];
+
+        for strategy in [&Greedy as &dyn MatchStrategy, &Balanced, &Clustered] {
+            let assignments = strategy.assign(&rides, &drivers);
+            assert_eq!(cars(&assignments, &drivers), vec![
+                vec![],
+                vec![rides[0].rider_id, rides[1].rider_id]
+            ]);
+        }
+    }
+
+    #[test]
+    fn groups_too_big_for_any_car_wait_without_blocking_others() {
+        let mut rides = group(&[0, 1, 2]);
+        rides.push(ride(3));
+        let drivers = vec![driver(2, 0), driver(2, 0)];
+
+        for strategy in [&Greedy as &dyn MatchStrategy, &Balanced, &Clustered] {
+            let assignments = strategy.assign(&rides, &drivers);
+            let seated: Vec<Uuid> = assignments.iter().map(|assignment| assignment.rider_id).collect();
+            assert_eq!(seated, vec![rides[3].rider_id]);
+        }
+
+        let solo = rides[3].rider_id;
+        let waiting: Vec<Uuid> = waitlist_head(rides, &drivers).iter().map(|ride| ride.rider_id).collect();
+        assert_eq!(waiting, vec![solo]);
+    }
+}
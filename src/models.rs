@@ -1,68 +1,249 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
 use sqlite::Value;
 use uuid::Uuid;
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDateTime};
+
+/// Direction of a trip for an event.
+/// Riders and drivers sign up for each direction on its own
+#[derive(Clone, Copy, PartialEq)]
+pub enum Leg {
+    /// From campus to the event
+    To,
+    /// From the event back to campus
+    Return
+}
 
-/// Available campus locations
-/// A driver can only give rides for people on their campus
-pub enum Campus {
-    /// Rochester Institute of Technology
-    RIT,
-    /// University of Rochester
-    UofR,
-    /// Both campuses
-    Both
+impl Leg {
+    /// Name shown to users
+    pub fn label(&self) -> &'static str {
+        match self {
+            Leg::To => "To Event",
+            Leg::Return => "Return"
+        }
+    }
 }
 
-impl From<&str> for Campus {
+impl From<&str> for Leg {
     fn from(s: &str) -> Self {
         match s {
-            "RIT" => Campus::RIT,
-            "UofR" => Campus::UofR,
-            _ => Campus::Both
+            "RETURN" => Leg::Return,
+            _ => Leg::To
         }
     }
 }
 
-impl Into<&'static str> for Campus {
-    fn into(self) -> &'static str {
-        match self {
-            Campus::RIT => "RIT",
-            Campus::UofR => "UR",
-            Campus::Both => "BOTH"
+impl From<Leg> for &'static str {
+    fn from(leg: Leg) -> Self {
+        match leg {
+            Leg::To => "TO",
+            Leg::Return => "RETURN"
+        }
+    }
+}
+
+/// Short code of a campus, as stored on rides and drivers.
+/// Codes are always trimmed and uppercase, so the same campus is never
+/// stored two different ways
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CampusCode(String);
+
+impl CampusCode {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for CampusCode {
+    type Err = ParseCampusCodeError;
+
+    /// Parse a code typed by a user or stored in the database.
+    /// This only checks the code is well formed, `db::get_campus` checks it exists
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().to_uppercase();
+
+        if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(ParseCampusCodeError(s.to_string()));
+        }
+
+        // Drivers used to serve "BOTH" campuses, they now list each campus
+        if code == "BOTH" {
+            return Err(ParseCampusCodeError(s.to_string()));
+        }
+
+        Ok(CampusCode(code))
+    }
+}
+
+impl fmt::Display for CampusCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A campus code that is empty, contains anything but letters and numbers,
+/// or is the old "BOTH" value
+#[derive(Debug)]
+pub struct ParseCampusCodeError(String);
+
+impl fmt::Display for ParseCampusCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid campus code: {:?}", self.0)
+    }
+}
+
+impl Error for ParseCampusCodeError {}
+
+/// A campus or pickup zone, managed by leadership.
+/// Riders are picked up from one campus, drivers can serve any number of them
+pub struct Campus {
+    /// Short code stored on rides and drivers
+    pub code: CampusCode,
+    pub name: String,
+    /// Known places on the campus where riders can be picked up
+    pub pickup_points: Vec<PickupPoint>
+}
+
+impl From<&[Value]> for Campus {
+    fn from(row: &[Value]) -> Self {
+        let code = row[0].as_string().unwrap().parse().unwrap();
+        let name = row[1].as_string().unwrap().to_string();
+
+        Campus {
+            code,
+            name,
+            pickup_points: Vec::new()
+        }
+    }
+}
+
+impl Campus {
+    /// Match a rider's free text pickup location to a known pickup point
+    pub fn normalize_pickup(&self, location: &str) -> Option<&PickupPoint> {
+        // Pad with spaces so aliases only match whole words
+        let words: Vec<String> = location.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect();
+        let location = format!(" {} ", words.join(" "));
+
+        self.pickup_points.iter().find(|point| {
+            point.aliases.iter().any(|alias| location.contains(&format!(" {alias} ")))
+        })
+    }
+}
+
+/// A known place on a campus where riders can be picked up
+pub struct PickupPoint {
+    pub name: String,
+    /// Points in the same area are close enough to share a car
+    pub area: String,
+    /// Lowercase names riders commonly use for this point
+    pub aliases: Vec<String>
+}
+
+impl From<&[Value]> for PickupPoint {
+    fn from(row: &[Value]) -> Self {
+        let name = row[0].as_string().unwrap().to_string();
+        let area = row[1].as_string().unwrap().to_string();
+        let aliases = row[2].as_string().unwrap()
+            .split(',')
+            .map(|alias| alias.trim().to_lowercase())
+            .filter(|alias| !alias.is_empty())
+            .collect();
+
+        PickupPoint {
+            name,
+            area,
+            aliases
         }
     }
 }
 
-impl Into<String> for Campus {
-    fn into(self) -> String {
+/// What a user is allowed to do.
+/// Each role can do everything the roles before it can
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Role {
+    /// Asked for a ride without an account, can only see their ride's status
+    Guest,
+    /// Can ride, drive and manage their own sign ups
+    Member,
+    /// Can also create events, see who is riding with who and move riders
+    Organizer,
+    /// Can also manage campuses, every event and other users' roles
+    Admin
+}
+
+impl Role {
+    /// Every role an admin can give, lowest first
+    pub const ALL: [Role; 3] = [Role::Member, Role::Organizer, Role::Admin];
+
+    /// Name shown to users
+    pub fn label(&self) -> &'static str {
+        match self {
+            Role::Guest => "Guest",
+            Role::Member => "Member",
+            Role::Organizer => "Event Organizer",
+            Role::Admin => "Admin"
+        }
+    }
+
+    /// Stored form of the role
+    pub fn code(&self) -> &'static str {
         match self {
-            Campus::RIT => String::from("RIT"),
-            Campus::UofR => String::from("UR"),
-            Campus::Both => String::from("BOTH")
+            Role::Guest => "GUEST",
+            Role::Member => "MEMBER",
+            Role::Organizer => "ORGANIZER",
+            Role::Admin => "ADMIN"
+        }
+    }
+}
+
+impl From<&str> for Role {
+    fn from(s: &str) -> Self {
+        match s {
+            "ADMIN" => Role::Admin,
+            "ORGANIZER" => Role::Organizer,
+            "GUEST" => Role::Guest,
+            _ => Role::Member
         }
     }
 }
 
+impl From<Role> for &'static str {
+    fn from(role: Role) -> Self {
+        role.code()
+    }
+}
+
 /// A User of the App
 /// Can act as a driver or a rider
 pub struct User {
     /// Unique User ID
     pub id: Uuid,
+    /// Empty for guests
     pub email: String,
     pub fullname: String,
-    /// Hashed using BCrypt
+    /// Hashed using BCrypt, empty for guests
     pub password: String,
     /// Phone Number
-    pub number: String
+    pub number: String,
+    pub role: Role,
+    /// Whether the user clicked the link sent to their email
+    pub verified: bool
 }
 
 impl From<&[Value]> for User {
     fn from(row: &[Value]) -> Self {
         let id = Uuid::parse_str(row[0].as_string().unwrap()).unwrap();
-        let email = row[1].as_string().unwrap().to_string();
+        let email = row[1].as_string().unwrap_or_default().to_string();
         let fullname = row[2].as_string().unwrap().to_string();
-        let password = row[3].as_string().unwrap().to_string();
+        let password = row[3].as_string().unwrap_or_default().to_string();
         let number = row[4].as_string().unwrap().to_string();
+        let role = row[5].as_string().unwrap().into();
+        let verified = row[6].as_integer().unwrap() != 0;
 
         User {
             id,
@@ -70,6 +251,8 @@ impl From<&[Value]> for User {
             fullname,
             password,
             number,
+            role,
+            verified,
         }
     }
 }
@@ -86,7 +269,34 @@ pub struct Event {
     pub state: String,
     pub zipcode: String,
     /// ID of the user who created and can delete this event
-    pub creator_id: Uuid
+    pub creator_id: Uuid,
+    /// Name of the matching strategy for this event, if not the default
+    pub strategy: Option<String>,
+    /// No new rides or drives can be added after this time
+    pub signup_deadline: Option<NaiveDateTime>,
+    /// The matcher stops changing assignments after this time
+    pub freeze_time: Option<NaiveDateTime>,
+    /// When the event was called off, cancelled events are kept as a record
+    pub cancelled_at: Option<NaiveDateTime>,
+    /// ID of the user who cancelled the event
+    pub cancelled_by: Option<Uuid>
+}
+
+impl Event {
+    /// Whether it is too late to sign up to ride or drive
+    pub fn signups_closed(&self, now: NaiveDateTime) -> bool {
+        self.signup_deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    /// Whether the event was called off
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled_at.is_some()
+    }
+
+    /// Whether assignments are locked in
+    pub fn is_frozen(&self, now: NaiveDateTime) -> bool {
+        self.freeze_time.is_some_and(|freeze| now >= freeze)
+    }
 }
 
 impl From<&[Value]> for Event {
@@ -103,6 +313,15 @@ impl From<&[Value]> for Event {
         let state = row[6].as_string().unwrap().to_string();
         let zipcode = row[7].as_string().unwrap().to_string();
         let creator_id = Uuid::parse_str(row[8].as_string().unwrap()).unwrap();
+        let strategy = row[9].as_string().map(String::from);
+        let signup_deadline = row[10].as_integer()
+            .map(|time| NaiveDateTime::from_timestamp(time, 0));
+        let freeze_time = row[11].as_integer()
+            .map(|time| NaiveDateTime::from_timestamp(time, 0));
+        let cancelled_at = row[12].as_integer()
+            .map(|time| NaiveDateTime::from_timestamp(time, 0));
+        let cancelled_by = row[13].as_string()
+            .map(|id| Uuid::parse_str(id).unwrap());
 
         Event {
             id,
@@ -113,7 +332,12 @@ impl From<&[Value]> for Event {
             city,
             state,
             zipcode,
-            creator_id
+            creator_id,
+            strategy,
+            signup_deadline,
+            freeze_time,
+            cancelled_at,
+            cancelled_by
         }
     }
 }
@@ -121,12 +345,35 @@ impl From<&[Value]> for Event {
 /// Event Metaobject, containing all information that a driver/rider would need
 pub struct EventData {
     pub event: Event,
+    /// Each direction of the trip the user signed up for
+    pub legs: Vec<LegData>
+}
+
+impl EventData {
+    /// Whether the user is driving any leg of the event
+    pub fn is_driver(&self) -> bool {
+        self.legs.iter().any(|leg| leg.is_driver)
+    }
+
+    /// Whether the user is riding any leg of the event
+    pub fn is_rider(&self) -> bool {
+        self.legs.iter().any(|leg| !leg.is_driver)
+    }
+}
+
+/// Everything a driver/rider would need for one direction of a trip
+pub struct LegData {
+    pub leg: Leg,
     /// List of tuples of riders and their pickup location
     pub riders: Option<Vec<(User, String)>>,
     /// Tuple of Drivers and their vehicle
     pub driver: Option<(User, Vehicle)>,
     /// Is Driver
-    pub is_driver: bool
+    pub is_driver: bool,
+    /// The rider's driver dropped out and a new one is being found
+    pub driver_cancelled: bool,
+    /// The rider's place in line for a seat, starting at 1
+    pub waitlist_position: Option<usize>
 }
 
 /// Information about a driver's vehicle
@@ -166,8 +413,10 @@ pub struct Driver {
     pub seats: i64,
     /// Vehicle id of the vehicle the driver will be driving
     pub vehicle_id: Uuid,
-    /// Campus the driver will be driving from
-    pub campus: Campus
+    /// Codes of the campuses the driver will pick up from
+    pub campuses: Vec<CampusCode>,
+    /// Direction the driver is driving
+    pub leg: Leg
 }
 
 impl From<&[Value]> for Driver {
@@ -176,14 +425,20 @@ impl From<&[Value]> for Driver {
         let driver_id = Uuid::parse_str(row[1].as_string().unwrap()).unwrap();
         let seats = row[2].as_integer().unwrap();
         let vehicle_id = Uuid::parse_str(row[3].as_string().unwrap()).unwrap();
-        let campus: Campus = row[4].as_string().unwrap().into();
+        let campuses = row[4].as_string().unwrap_or("")
+            .split(',')
+            .filter(|code| !code.is_empty())
+            .map(|code| code.parse().unwrap())
+            .collect();
+        let leg: Leg = row[5].as_string().unwrap().into();
 
         Driver {
             event_id,
             driver_id,
             seats,
             vehicle_id,
-            campus
+            campuses,
+            leg
         }
     }
 }
@@ -196,10 +451,26 @@ pub struct Ride {
     pub driver_id: Option<Uuid>,
     /// The id of the event
     pub event_id: Uuid,
-    /// The campus to be picked up from
-    pub campus: Campus,
+    /// Code of the campus to be picked up from
+    pub campus: CampusCode,
     /// The location the rider wants to be picked up
-    pub pickup_location: String
+    pub pickup_location: String,
+    /// Name of the known pickup point the location matched, if any
+    pub pickup_point: Option<String>,
+    /// Area of the matched pickup point, nearby points share an area
+    pub pickup_area: Option<String>,
+    /// Riders in the same group must share a car
+    pub group_id: Option<Uuid>,
+    /// The rider's driver dropped out and they are waiting for a new one
+    pub driver_cancelled: bool,
+    /// Direction the rider needs a ride
+    pub leg: Leg,
+    /// Set by leadership, the matcher never changes a pinned ride
+    pub pinned: bool,
+    /// When the rider asked for a ride, used to order the waitlist
+    pub request_time: NaiveDateTime,
+    /// Number of past events the rider was given a ride to
+    pub rides_taken: i64
 }
 
 impl From<&[Value]> for Ride {
@@ -211,40 +482,105 @@ impl From<&[Value]> for Ride {
             Some(Uuid::parse_str(row[1].as_string().unwrap()).unwrap())
         };
         let event_id = Uuid::parse_str(row[2].as_string().unwrap()).unwrap();
-        let campus: Campus = row[3].as_string().unwrap().into();
+        let campus = row[3].as_string().unwrap().parse().unwrap();
         let pickup_location = row[4].as_string().unwrap().to_string();
+        let pickup_point = row[5].as_string().map(String::from);
+        let group_id = row[6].as_string().map(|id| Uuid::parse_str(id).unwrap());
+        let driver_cancelled = row[7].as_integer().unwrap() != 0;
+        let leg: Leg = row[8].as_string().unwrap().into();
+        let pickup_area = row[9].as_string().map(String::from);
+        let pinned = row[10].as_integer().unwrap() != 0;
+        let request_time = NaiveDateTime::from_timestamp(
+            row[11].as_integer().unwrap(),
+            0
+        );
+        let rides_taken = row[12].as_integer().unwrap();
 
         Ride {
             rider_id,
             driver_id,
             event_id,
             campus,
-            pickup_location
+            pickup_location,
+            pickup_point,
+            pickup_area,
+            group_id,
+            driver_cancelled,
+            leg,
+            pinned,
+            request_time,
+            rides_taken
         }
     }
 }
 
-/// Info about a single event
-pub struct EventInfo {
-    pub name: String,
-    pub riders: usize,
-    pub unassigned: usize
+/// A change leadership made to a rider's assignment
+pub struct AssignmentChange {
+    pub event_id: Uuid,
+    pub rider_id: Uuid,
+    pub leg: Leg,
+    /// The rider's new driver, if they weren't unassigned
+    pub driver_id: Option<Uuid>,
+    pub pinned: bool,
+    /// The user who made the change
+    pub changed_by: Uuid,
+    pub change_time: NaiveDateTime
 }
 
-impl From<&[Value]> for EventInfo {
+impl From<&[Value]> for AssignmentChange {
     fn from(row: &[Value]) -> Self {
-        let name = row[0].as_string().unwrap().to_string();
-        let riders = row[1].as_integer().unwrap() as usize;
-        let unassigned = row[2].as_integer().unwrap() as usize;
+        let event_id = Uuid::parse_str(row[0].as_string().unwrap()).unwrap();
+        let rider_id = Uuid::parse_str(row[1].as_string().unwrap()).unwrap();
+        let leg: Leg = row[2].as_string().unwrap().into();
+        let driver_id = row[3].as_string().map(|id| Uuid::parse_str(id).unwrap());
+        let pinned = row[4].as_integer().unwrap() != 0;
+        let changed_by = Uuid::parse_str(row[5].as_string().unwrap()).unwrap();
+        let change_time = NaiveDateTime::from_timestamp(
+            row[6].as_integer().unwrap(),
+            0
+        );
 
-        EventInfo {
-            name,
-            riders,
-            unassigned
+        AssignmentChange {
+            event_id,
+            rider_id,
+            leg,
+            driver_id,
+            pinned,
+            changed_by,
+            change_time
         }
     }
 }
 
+/// Who is riding with who for a single event, for leadership
+pub struct EventInfo {
+    pub event: Event,
+    /// Every car for the event, one per driver and leg
+    pub cars: Vec<CarInfo>,
+    /// Riders still waiting for a driver, with their ride
+    pub unassigned: Vec<(User, Ride)>
+}
+
+impl EventInfo {
+    /// Number of riders signed up for the event, counting each leg
+    pub fn riders(&self) -> usize {
+        self.cars.iter().map(|car| car.passengers.len()).sum::<usize>() + self.unassigned.len()
+    }
+}
+
+/// One driver's car for one leg of an event
+pub struct CarInfo {
+    pub driver: User,
+    pub vehicle: Vehicle,
+    pub leg: Leg,
+    /// Campuses the driver picks up from
+    pub campuses: Vec<CampusCode>,
+    /// Total seats offered
+    pub seats: i64,
+    /// Riders in the car with where to pick them up
+    pub passengers: Vec<(User, String)>
+}
+
 /// A users password reset request
 pub struct ResetRequest {
     pub user_id: Uuid,
@@ -252,6 +588,23 @@ pub struct ResetRequest {
     pub request_time: NaiveDateTime
 }
 
+impl ResetRequest {
+    /// How long a reset link works for after it is sent.
+    /// Set in minutes by RESET_LIFETIME_MINUTES, defaults to an hour
+    pub fn lifetime() -> Duration {
+        std::env::var("RESET_LIFETIME_MINUTES").ok()
+            .and_then(|minutes| minutes.parse().ok())
+            .filter(|minutes| *minutes > 0)
+            .map(Duration::minutes)
+            .unwrap_or_else(|| Duration::hours(1))
+    }
+
+    /// Whether the link is too old to reset a password with
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        now - self.request_time >= Self::lifetime()
+    }
+}
+
 impl From<&[Value]> for ResetRequest {
     fn from(row: &[Value]) -> Self {
         let user_id = Uuid::parse_str(row[0].as_string().unwrap()).unwrap();
@@ -268,3 +621,277 @@ impl From<&[Value]> for ResetRequest {
         }
     }
 }
+
+/// A single use link that logs a user in without their password
+pub struct LoginToken {
+    pub user_id: Uuid,
+    pub token_id: Uuid,
+    pub request_time: NaiveDateTime
+}
+
+impl LoginToken {
+    /// How long a login link works for after it is sent
+    pub fn lifetime() -> Duration {
+        Duration::minutes(15)
+    }
+
+    /// Whether the link is too old to log in with
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        now - self.request_time >= Self::lifetime()
+    }
+}
+
+impl From<&[Value]> for LoginToken {
+    fn from(row: &[Value]) -> Self {
+        let user_id = Uuid::parse_str(row[0].as_string().unwrap()).unwrap();
+        let token_id = Uuid::parse_str(row[1].as_string().unwrap()).unwrap();
+        let request_time = NaiveDateTime::from_timestamp(
+            row[2].as_integer().unwrap(),
+            0
+        );
+
+        Self {
+            user_id,
+            token_id,
+            request_time
+        }
+    }
+}
+
+/// A link sent to a user to prove they own their email
+pub struct Verification {
+    pub user_id: Uuid,
+    pub token_id: Uuid,
+    pub request_time: NaiveDateTime
+}
+
+impl Verification {
+    /// How long a verification link works for after it is sent
+    pub fn lifetime() -> Duration {
+        Duration::days(1)
+    }
+
+    /// Whether the link is too old to verify with
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        now - self.request_time >= Self::lifetime()
+    }
+}
+
+impl From<&[Value]> for Verification {
+    fn from(row: &[Value]) -> Self {
+        let user_id = Uuid::parse_str(row[0].as_string().unwrap()).unwrap();
+        let token_id = Uuid::parse_str(row[1].as_string().unwrap()).unwrap();
+        let request_time = NaiveDateTime::from_timestamp(
+            row[2].as_integer().unwrap(),
+            0
+        );
+
+        Self {
+            user_id,
+            token_id,
+            request_time
+        }
+    }
+}
+
+/// A browser a user chose to stay logged in on.
+/// The token that logs the device in is only kept in the device's cookie
+pub struct Device {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// Browser the device logged in with
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub last_seen: NaiveDateTime
+}
+
+impl Device {
+    /// How long a device stays logged in without being used
+    pub fn lifetime() -> Duration {
+        Duration::days(90)
+    }
+}
+
+impl From<&[Value]> for Device {
+    fn from(row: &[Value]) -> Self {
+        let id = Uuid::parse_str(row[0].as_string().unwrap()).unwrap();
+        let user_id = Uuid::parse_str(row[1].as_string().unwrap()).unwrap();
+        let name = row[2].as_string().unwrap_or_default().to_string();
+        let created_at = NaiveDateTime::from_timestamp(row[3].as_integer().unwrap(), 0);
+        let last_seen = NaiveDateTime::from_timestamp(row[4].as_integer().unwrap(), 0);
+
+        Self {
+            id,
+            user_id,
+            name,
+            created_at,
+            last_seen
+        }
+    }
+}
+
+/// A link that lets someone create an account
+pub struct Invite {
+    pub id: Uuid,
+    /// Admin who made the invite, missing for the old shared INVITE_ID link
+    pub created_by: Option<Uuid>,
+    /// Only this email can sign up with the invite, if set
+    pub email: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    /// How many accounts can be made with the invite, unlimited if not set
+    pub max_uses: Option<i64>,
+    pub uses: i64,
+    pub revoked: bool
+}
+
+impl Invite {
+    /// Check that someone can still sign up with the invite,
+    /// returning why not if they can't
+    pub fn check(&self, now: NaiveDateTime) -> Result<(), &'static str> {
+        if self.revoked {
+            return Err("This invite has been revoked");
+        }
+
+        if self.expires_at.is_some_and(|expires| now >= expires) {
+            return Err("This invite has expired");
+        }
+
+        if self.max_uses.is_some_and(|max| self.uses >= max) {
+            return Err("This invite has already been used");
+        }
+
+        Ok(())
+    }
+
+    /// Whether the invite can be used by someone signing up with this email
+    pub fn allows_email(&self, email: &str) -> bool {
+        self.email.as_deref().is_none_or(|invited| invited.eq_ignore_ascii_case(email.trim()))
+    }
+}
+
+impl From<&[Value]> for Invite {
+    fn from(row: &[Value]) -> Self {
+        let id = Uuid::parse_str(row[0].as_string().unwrap()).unwrap();
+        let created_by = row[1].as_string().and_then(|id| Uuid::parse_str(id).ok());
+        let email = row[2].as_string().map(String::from);
+        let created_at = NaiveDateTime::from_timestamp(row[3].as_integer().unwrap(), 0);
+        let expires_at = row[4].as_integer()
+            .map(|time| NaiveDateTime::from_timestamp(time, 0));
+        let max_uses = row[5].as_integer();
+        let uses = row[6].as_integer().unwrap();
+        let revoked = row[7].as_integer().unwrap() != 0;
+
+        Self {
+            id,
+            created_by,
+            email,
+            created_at,
+            expires_at,
+            max_uses,
+            uses,
+            revoked
+        }
+    }
+}
+
+/// A request from a rider for another user to share their car on one leg of an event.
+/// Nothing about the invited user's ride changes until they accept
+pub struct GroupInvite {
+    pub id: Uuid,
+    pub event_id: Uuid,
+    pub leg: Leg,
+    /// Group the invited user joins when they accept
+    pub group_id: Uuid,
+    pub invited_by: Uuid,
+    pub user_id: Uuid,
+    pub created_at: NaiveDateTime
+}
+
+impl From<&[Value]> for GroupInvite {
+    fn from(row: &[Value]) -> Self {
+        let id = Uuid::parse_str(row[0].as_string().unwrap()).unwrap();
+        let event_id = Uuid::parse_str(row[1].as_string().unwrap()).unwrap();
+        let leg: Leg = row[2].as_string().unwrap().into();
+        let group_id = Uuid::parse_str(row[3].as_string().unwrap()).unwrap();
+        let invited_by = Uuid::parse_str(row[4].as_string().unwrap()).unwrap();
+        let user_id = Uuid::parse_str(row[5].as_string().unwrap()).unwrap();
+        let created_at = NaiveDateTime::from_timestamp(row[6].as_integer().unwrap(), 0);
+
+        Self {
+            id,
+            event_id,
+            leg,
+            group_id,
+            invited_by,
+            user_id,
+            created_at
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn campus_codes_are_trimmed_and_uppercase() {
+        let code: CampusCode = " rit ".parse().unwrap();
+        assert_eq!(code.as_str(), "RIT");
+        assert_eq!(code, "RIT".parse().unwrap());
+        assert_eq!("Uofr2".parse::<CampusCode>().unwrap().as_str(), "UOFR2");
+    }
+
+    #[test]
+    fn malformed_campus_codes_are_rejected() {
+        for code in ["", "   ", "R IT", "RIT-2", "both", "BOTH", " Both "] {
+            assert!(code.parse::<CampusCode>().is_err(), "{code:?} parsed");
+        }
+    }
+
+    #[test]
+    fn roles_are_ordered_by_what_they_can_do() {
+        assert!(Role::Guest < Role::Member);
+        assert!(Role::Member < Role::Organizer);
+        assert!(Role::Organizer < Role::Admin);
+        assert!(Role::ALL.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn roles_round_trip_through_their_codes() {
+        for role in [Role::Guest, Role::Member, Role::Organizer, Role::Admin] {
+            assert_eq!(Role::from(role.code()), role);
+        }
+        assert_eq!(Role::from("SUPERUSER"), Role::Member);
+    }
+
+    fn campus() -> Campus {
+        let point = |name: &str, area: &str, aliases: &[&str]| PickupPoint {
+            name: name.to_string(),
+            area: area.to_string(),
+            aliases: aliases.iter().map(|alias| alias.to_string()).collect()
+        };
+
+        Campus {
+            code: "MAIN".parse().unwrap(),
+            name: "Main Campus".to_string(),
+            pickup_points: vec![
+                point("Library", "North", &["library", "lib"]),
+                point("North Gate", "North", &["north gate"])
+            ]
+        }
+    }
+
+    #[test]
+    fn pickup_locations_match_aliases_as_whole_words() {
+        let campus = campus();
+        let name = |location| campus.normalize_pickup(location).map(|point| point.name.as_str());
+
+        assert_eq!(name("Outside the LIBRARY"), Some("Library"));
+        assert_eq!(name("lib steps"), Some("Library"));
+        assert_eq!(name("North-Gate, by the bus stop"), Some("North Gate"));
+        assert_eq!(name("Liberty Hall"), None);
+        assert_eq!(name("north parking gate"), None);
+        assert_eq!(name(""), None);
+    }
+}
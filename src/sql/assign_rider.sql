@@ -1,4 +1,6 @@
 UPDATE rides
-SET driver_id = ?
+SET driver_id = ?,
+	driver_cancelled = 0
 WHERE event_id = ?
-	AND rider_id = ?;
+	AND rider_id = ?
+	AND leg = ?;
@@ -0,0 +1,7 @@
+UPDATE rides
+SET driver_id = NULL,
+    driver_cancelled = 1,
+    pinned = 0
+WHERE event_id = ?
+    AND rider_id = ?
+    AND leg = ?;
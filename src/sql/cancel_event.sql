@@ -0,0 +1,4 @@
+UPDATE events
+SET cancelled_at = ?,
+    cancelled_by = ?
+WHERE id = ?;
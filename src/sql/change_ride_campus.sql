@@ -0,0 +1,15 @@
+UPDATE rides
+SET campus = ?1,
+    driver_id = NULL,
+    driver_cancelled = 0
+WHERE event_id = ?2
+    AND (
+        rider_id = ?3
+        OR group_id IN (
+            SELECT group_id
+            FROM rides
+            WHERE event_id = ?2
+                AND rider_id = ?3
+                AND group_id IS NOT NULL
+        )
+    );
@@ -0,0 +1,15 @@
+SELECT (
+    SELECT COUNT(*)
+    FROM rides r
+        INNER JOIN events e ON e.id = r.event_id
+    WHERE r.campus = ?1
+        AND e.time >= ?2
+        AND e.cancelled_at IS NULL
+) + (
+    SELECT COUNT(*)
+    FROM driver_campuses dc
+        INNER JOIN events e ON e.id = dc.event_id
+    WHERE dc.campus = ?1
+        AND e.time >= ?2
+        AND e.cancelled_at IS NULL
+);
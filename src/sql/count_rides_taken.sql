@@ -0,0 +1,9 @@
+UPDATE users
+SET rides_taken = rides_taken + (
+    SELECT COUNT(DISTINCT rides.event_id)
+    FROM rides
+        INNER JOIN events ON events.id = rides.event_id
+    WHERE rides.rider_id = users.id
+        AND rides.driver_id IS NOT NULL
+        AND events.time < ?1
+);
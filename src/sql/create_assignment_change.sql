@@ -0,0 +1,9 @@
+INSERT INTO assignment_changes (
+    event_id,
+    rider_id,
+    leg,
+    driver_id,
+    pinned,
+    changed_by,
+    change_time
+) VALUES (?, ?, ?, ?, ?, ?, ?);
@@ -0,0 +1,4 @@
+INSERT INTO campuses (
+    code,
+    name
+) VALUES (?, ?);
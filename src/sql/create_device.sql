@@ -0,0 +1,8 @@
+INSERT INTO devices (
+    id,
+    user_id,
+    token,
+    name,
+    created_at,
+    last_seen
+) VALUES (?1, ?2, ?3, ?4, ?5, ?5);
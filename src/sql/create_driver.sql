@@ -3,5 +3,5 @@ INSERT INTO drivers (
     event_id,
     vehicle_id,
     seats,
-    campus
+    leg
 ) VALUES (?, ?, ?, ?, ?);
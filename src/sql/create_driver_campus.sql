@@ -0,0 +1,6 @@
+INSERT INTO driver_campuses (
+    event_id,
+    driver_id,
+    leg,
+    campus
+) VALUES (?, ?, ?, ?);
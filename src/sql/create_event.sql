@@ -7,5 +7,8 @@ INSERT INTO events (
     city,
     state,
     zipcode,
-    creator_id
-) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
+    creator_id,
+    strategy,
+    signup_deadline,
+    freeze_time
+) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
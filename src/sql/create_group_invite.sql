@@ -0,0 +1,9 @@
+INSERT INTO group_invites (
+    id,
+    event_id,
+    leg,
+    group_id,
+    invited_by,
+    user_id,
+    created_at
+) VALUES (?, ?, ?, ?, ?, ?, ?);
@@ -0,0 +1,7 @@
+INSERT INTO users (
+    id,
+    fullname,
+    number,
+    role,
+    guest_token
+) VALUES (?, ?, ?, 'GUEST', ?);
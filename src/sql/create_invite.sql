@@ -0,0 +1,8 @@
+INSERT OR IGNORE INTO invites (
+    id,
+    created_by,
+    email,
+    created_at,
+    expires_at,
+    max_uses
+) VALUES (?, ?, ?, ?, ?, ?);
@@ -0,0 +1,5 @@
+INSERT INTO login_tokens (
+    user_id,
+    token_id,
+    request_time
+) VALUES (?, ?, ?);
@@ -0,0 +1,6 @@
+INSERT INTO pickup_points (
+    campus,
+    name,
+    area,
+    aliases
+) VALUES (?, ?, ?, ?);
@@ -2,5 +2,9 @@ INSERT INTO rides (
     rider_id,
     event_id,
     campus,
-    pickup_location
-) VALUES (?, ?, ?, ?);
+    pickup_location,
+    pickup_point,
+    group_id,
+    leg,
+    request_time
+) VALUES (?, ?, ?, ?, ?, ?, ?, ?);
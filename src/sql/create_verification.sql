@@ -0,0 +1,5 @@
+INSERT INTO verifications (
+    user_id,
+    token_id,
+    request_time
+) VALUES (?, ?, ?);
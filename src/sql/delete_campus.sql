@@ -0,0 +1,2 @@
+DELETE FROM campuses
+WHERE code = ?;
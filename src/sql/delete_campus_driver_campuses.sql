@@ -0,0 +1,2 @@
+DELETE FROM driver_campuses
+WHERE campus = ?;
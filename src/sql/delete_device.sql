@@ -0,0 +1,3 @@
+DELETE FROM devices
+WHERE id = ?
+    AND user_id = ?;
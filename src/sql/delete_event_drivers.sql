@@ -0,0 +1,2 @@
+DELETE FROM drivers
+WHERE event_id = ?;
@@ -0,0 +1,2 @@
+DELETE FROM rides
+WHERE event_id = ?;
@@ -0,0 +1,2 @@
+DELETE FROM login_tokens
+WHERE request_time < ?;
@@ -0,0 +1,2 @@
+DELETE FROM resets
+WHERE request_time < ?;
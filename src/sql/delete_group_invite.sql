@@ -0,0 +1,2 @@
+DELETE FROM group_invites
+WHERE id = ?;
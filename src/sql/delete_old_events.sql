@@ -1,2 +1,3 @@
 DELETE FROM events
-WHERE time < ?;
+WHERE time < ?
+    AND cancelled_at IS NULL;
@@ -0,0 +1,6 @@
+DELETE FROM group_invites
+WHERE event_id NOT IN (
+    SELECT id
+    FROM events
+    WHERE cancelled_at IS NULL
+);
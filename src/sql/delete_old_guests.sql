@@ -0,0 +1,7 @@
+DELETE FROM users
+WHERE role = 'GUEST'
+    AND id NOT IN (
+        SELECT r.rider_id
+        FROM rides r
+            INNER JOIN events e ON e.id = r.event_id
+    );
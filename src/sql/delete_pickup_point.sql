@@ -0,0 +1,2 @@
+DELETE FROM pickup_points
+WHERE campus = ? AND name = ?;
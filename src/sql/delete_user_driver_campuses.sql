@@ -0,0 +1,2 @@
+DELETE FROM driver_campuses
+WHERE driver_id = ? AND event_id = ?;
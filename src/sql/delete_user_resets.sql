@@ -0,0 +1,2 @@
+DELETE FROM resets
+WHERE user_id = ?;
@@ -1,3 +1,5 @@
 UPDATE rides
-SET driver_id = NULL
+SET driver_id = NULL,
+    driver_cancelled = 1,
+    pinned = 0
 WHERE driver_id = ? AND event_id = ?;
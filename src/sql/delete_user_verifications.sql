@@ -0,0 +1,2 @@
+DELETE FROM verifications
+WHERE user_id = ?;
@@ -0,0 +1,2 @@
+DELETE FROM vehicles
+WHERE id = ?;
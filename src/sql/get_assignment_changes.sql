@@ -0,0 +1,11 @@
+SELECT
+    event_id,
+    rider_id,
+    leg,
+    driver_id,
+    pinned,
+    changed_by,
+    change_time
+FROM assignment_changes
+WHERE event_id = ?
+ORDER BY change_time DESC;
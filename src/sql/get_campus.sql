@@ -0,0 +1,6 @@
+SELECT
+    code,
+    name
+FROM campuses
+WHERE code = ?
+LIMIT 1;
@@ -0,0 +1,5 @@
+SELECT
+    code,
+    name
+FROM campuses
+ORDER BY name;
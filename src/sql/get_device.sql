@@ -0,0 +1,9 @@
+SELECT
+    id,
+    user_id,
+    name,
+    created_at,
+    last_seen
+FROM devices
+WHERE id = ?
+LIMIT 1;
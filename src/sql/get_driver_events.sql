@@ -1,4 +1,4 @@
-SELECT
+SELECT DISTINCT
     e.id,
     e.name,
     e.time,
@@ -7,7 +7,12 @@ SELECT
     e.city,
     e.state,
     e.zipcode,
-    e.creator_id
+    e.creator_id,
+    e.strategy,
+    e.signup_deadline,
+    e.freeze_time,
+    e.cancelled_at,
+    e.cancelled_by
 FROM events e
     LEFT JOIN drivers d ON d.event_id = e.id
 WHERE d.driver_id = ?
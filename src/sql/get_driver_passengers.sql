@@ -4,9 +4,12 @@ SELECT
     u.fullname,
     u.password,
     u.number,
+    u.role,
+    u.verified,
     r.campus,
     r.pickup_location
 FROM rides r
     LEFT JOIN users u ON u.id = r.rider_id
 WHERE r.event_id = ?
-    AND r.driver_id = ?;
+    AND r.driver_id = ?
+    AND r.leg = ?;
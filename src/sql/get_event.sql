@@ -0,0 +1,18 @@
+SELECT
+    id,
+    name,
+    time,
+    address1,
+    address2,
+    city,
+    state,
+    zipcode,
+    creator_id,
+    strategy,
+    signup_deadline,
+    freeze_time,
+    cancelled_at,
+    cancelled_by
+FROM events
+WHERE id = ?
+LIMIT 1;
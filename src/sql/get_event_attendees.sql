@@ -0,0 +1,15 @@
+SELECT
+    id,
+    email,
+    fullname,
+    password,
+    number,
+    role,
+    verified
+FROM users
+WHERE id IN (
+    SELECT rider_id FROM rides WHERE event_id = ?1
+    UNION
+    SELECT driver_id FROM drivers WHERE event_id = ?1
+)
+    AND email IS NOT NULL;
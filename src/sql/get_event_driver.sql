@@ -4,6 +4,8 @@ SELECT
     u.fullname,
     u.password,
     u.number,
+    u.role,
+    u.verified,
     v.id,
     v.user_id,
     v.color,
@@ -11,8 +13,11 @@ SELECT
     v.model
 FROM drivers d
 	INNER JOIN rides r ON r.driver_id = d.driver_id
+        AND r.event_id = d.event_id
+        AND r.leg = d.leg
     LEFT JOIN vehicles v ON d.vehicle_id = v.id
     LEFT JOIN users u ON u.id = d.driver_id
 WHERE d.event_id = ?
-    AND r.rider_id = ?;
+    AND r.rider_id = ?
+    AND r.leg = ?;
 
@@ -0,0 +1,21 @@
+SELECT
+    drivers.event_id,
+    drivers.driver_id,
+    drivers.seats,
+    drivers.vehicle_id,
+    (
+        SELECT GROUP_CONCAT(dc.campus)
+        FROM driver_campuses dc
+        WHERE dc.event_id = drivers.event_id
+            AND dc.driver_id = drivers.driver_id
+            AND dc.leg = drivers.leg
+    ) AS campuses,
+    drivers.leg,
+	SUM(CASE WHEN rides.rider_id IS NULL THEN 0 ELSE 1 END) AS rider_count
+FROM drivers
+	LEFT JOIN rides ON rides.driver_id = drivers.driver_id
+		AND rides.event_id = drivers.event_id
+		AND rides.leg = drivers.leg
+WHERE drivers.event_id = ?
+GROUP BY drivers.driver_id, drivers.leg
+ORDER BY drivers.leg DESC;
@@ -7,6 +7,12 @@ SELECT
     city,
     state,
     zipcode,
-    creator_id
+    creator_id,
+    strategy,
+    signup_deadline,
+    freeze_time,
+    cancelled_at,
+    cancelled_by
 FROM events
+WHERE cancelled_at IS NULL
 ORDER BY time;
@@ -0,0 +1,11 @@
+SELECT
+    id,
+    event_id,
+    leg,
+    group_id,
+    invited_by,
+    user_id,
+    created_at
+FROM group_invites
+WHERE id = ?
+LIMIT 1;
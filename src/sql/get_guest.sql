@@ -0,0 +1,12 @@
+SELECT
+    id,
+    email,
+    fullname,
+    password,
+    number,
+    role,
+    verified
+FROM users
+WHERE guest_token = ?
+    AND role = 'GUEST'
+LIMIT 1;
@@ -0,0 +1,12 @@
+SELECT
+    id,
+    created_by,
+    email,
+    created_at,
+    expires_at,
+    max_uses,
+    uses,
+    revoked
+FROM invites
+WHERE id = ?
+LIMIT 1;
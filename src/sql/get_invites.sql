@@ -0,0 +1,11 @@
+SELECT
+    id,
+    created_by,
+    email,
+    created_at,
+    expires_at,
+    max_uses,
+    uses,
+    revoked
+FROM invites
+ORDER BY created_at DESC;
@@ -0,0 +1,7 @@
+SELECT
+    user_id,
+    token_id,
+    request_time
+FROM login_tokens
+WHERE token_id = ?
+LIMIT 1;
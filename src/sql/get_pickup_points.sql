@@ -0,0 +1,7 @@
+SELECT
+    name,
+    area,
+    aliases
+FROM pickup_points
+WHERE campus = ?
+ORDER BY area, name;
@@ -0,0 +1,22 @@
+SELECT
+    r.rider_id,
+    r.driver_id,
+    r.event_id,
+    r.campus,
+    r.pickup_location,
+    r.pickup_point,
+    r.group_id,
+    r.driver_cancelled,
+    r.leg,
+    p.area,
+    r.pinned,
+    r.request_time,
+    COALESCE(u.rides_taken, 0)
+FROM rides r
+    LEFT JOIN pickup_points p ON p.campus = r.campus
+        AND p.name = r.pickup_point
+    LEFT JOIN users u ON u.id = r.rider_id
+WHERE r.event_id = ?
+    AND r.rider_id = ?
+    AND r.leg = ?
+LIMIT 1;
@@ -1,11 +1,25 @@
 SELECT
-	rider_id,
-	driver_id,
-	event_id,
-	campus,
-	pickup_location
+	rides.rider_id,
+	rides.driver_id,
+	rides.event_id,
+	rides.campus,
+	rides.pickup_location,
+	rides.pickup_point,
+	rides.group_id,
+	rides.driver_cancelled,
+	rides.leg,
+	pickup_points.area,
+	rides.pinned,
+	rides.request_time,
+	COALESCE(users.rides_taken, 0)
 FROM rides
+	LEFT JOIN pickup_points ON pickup_points.campus = rides.campus
+		AND pickup_points.name = rides.pickup_point
+	LEFT JOIN users ON users.id = rides.rider_id
 WHERE
 	rides.event_id = ?
 	AND rides.driver_id IS NULL
-	AND rides.campus = ?;
+	AND rides.pinned = 0
+	AND rides.leg = ?
+	AND rides.campus = ?
+ORDER BY rides.request_time, rides.rowid;
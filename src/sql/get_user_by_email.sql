@@ -3,7 +3,9 @@ SELECT
     email,
     fullname,
     password,
-    number
+    number,
+    role,
+    verified
 FROM users
-WHERE email = ?
+WHERE email = ? COLLATE NOCASE
 LIMIT 1;
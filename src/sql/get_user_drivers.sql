@@ -0,0 +1,16 @@
+SELECT
+    d.event_id,
+    d.driver_id,
+    d.seats,
+    d.vehicle_id,
+    (
+        SELECT GROUP_CONCAT(dc.campus)
+        FROM driver_campuses dc
+        WHERE dc.event_id = d.event_id
+            AND dc.driver_id = d.driver_id
+            AND dc.leg = d.leg
+    ) AS campuses,
+    d.leg
+FROM drivers d
+WHERE d.event_id = ?
+    AND d.driver_id = ?;
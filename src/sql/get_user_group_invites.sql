@@ -0,0 +1,13 @@
+SELECT
+    gi.id,
+    gi.event_id,
+    gi.leg,
+    gi.group_id,
+    gi.invited_by,
+    gi.user_id,
+    gi.created_at
+FROM group_invites gi
+    INNER JOIN events e ON e.id = gi.event_id
+WHERE gi.user_id = ?
+    AND e.cancelled_at IS NULL
+ORDER BY e.time, gi.created_at;
@@ -0,0 +1,13 @@
+SELECT
+    u.id,
+    u.email,
+    u.fullname,
+    u.password,
+    u.number,
+    u.role,
+    u.verified,
+    i.fullname
+FROM users u
+    LEFT JOIN users i ON i.id = u.invited_by
+WHERE u.role != 'GUEST'
+ORDER BY u.fullname COLLATE NOCASE;
@@ -0,0 +1,17 @@
+SELECT
+    d.event_id,
+    d.driver_id,
+    d.seats,
+    d.vehicle_id,
+    (
+        SELECT GROUP_CONCAT(dc.campus)
+        FROM driver_campuses dc
+        WHERE dc.event_id = d.event_id
+            AND dc.driver_id = d.driver_id
+            AND dc.leg = d.leg
+    ) AS campuses,
+    d.leg
+FROM drivers d
+    INNER JOIN events e ON e.id = d.event_id
+WHERE d.vehicle_id = ?
+    AND e.time >= ?;
@@ -0,0 +1,7 @@
+SELECT
+    user_id,
+    token_id,
+    request_time
+FROM verifications
+WHERE token_id = ?
+LIMIT 1;
@@ -0,0 +1,7 @@
+UPDATE rides
+SET group_id = ?1,
+    campus = ?2,
+    driver_id = NULL
+WHERE event_id = ?3
+    AND leg = ?5
+    AND (rider_id = ?4 OR group_id = ?1);
@@ -0,0 +1 @@
+ALTER TABLE rides ADD COLUMN group_id TEXT;
@@ -0,0 +1 @@
+ALTER TABLE rides ADD COLUMN driver_cancelled INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE rides ADD COLUMN leg TEXT NOT NULL DEFAULT 'TO';
+ALTER TABLE drivers ADD COLUMN leg TEXT NOT NULL DEFAULT 'TO';
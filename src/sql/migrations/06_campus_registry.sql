@@ -0,0 +1,53 @@
+CREATE TABLE IF NOT EXISTS campuses (
+    code TEXT PRIMARY KEY,
+    name TEXT
+);
+
+CREATE TABLE IF NOT EXISTS pickup_points (
+    campus TEXT,
+    name TEXT,
+    area TEXT,
+    aliases TEXT,
+    FOREIGN KEY (campus) REFERENCES campuses (code) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS driver_campuses (
+    event_id TEXT,
+    driver_id TEXT,
+    leg TEXT,
+    campus TEXT,
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE,
+    FOREIGN KEY (driver_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (campus) REFERENCES campuses (code) ON DELETE CASCADE
+);
+
+-- Campuses that used to be hardcoded, keeping the codes already stored in rides
+INSERT INTO campuses (code, name) VALUES
+    ('RIT', 'RIT'),
+    ('UR', 'UofR');
+
+INSERT INTO pickup_points (campus, name, area, aliases) VALUES
+    ('RIT', 'Gleason Circle', 'RIT Residence Halls', 'gleason'),
+    ('RIT', 'Grace Watson Hall', 'RIT Residence Halls', 'grace watson,gracies'),
+    ('RIT', 'Sol Heumann Hall', 'RIT Residence Halls', 'sol heumann,sol'),
+    ('RIT', 'Global Village', 'RIT Central', 'global village,gv'),
+    ('RIT', 'Student Alumni Union', 'RIT Central', 'student alumni union,sau'),
+    ('RIT', 'University Commons', 'RIT Apartments', 'university commons,uc'),
+    ('RIT', 'Province', 'RIT Apartments', 'province'),
+    ('RIT', 'Perkins Green', 'RIT Apartments', 'perkins'),
+    ('RIT', 'Riverknoll', 'RIT Apartments', 'riverknoll,river knoll'),
+    ('RIT', 'Park Point', 'RIT Apartments', 'park point'),
+    ('UR', 'Wilson Commons', 'UofR River Campus', 'wilson commons,wilco'),
+    ('UR', 'Rush Rhees Library', 'UofR River Campus', 'rush rhees'),
+    ('UR', 'Douglass Commons', 'UofR River Campus', 'douglass'),
+    ('UR', 'Susan B. Anthony Halls', 'UofR River Campus', 'susan b,sue b,sueb'),
+    ('UR', 'Residential Quad', 'UofR River Campus', 'residential quad,res quad,quad'),
+    ('UR', 'Southside', 'UofR South', 'southside,south side'),
+    ('UR', 'Riverview', 'UofR South', 'riverview,river view'),
+    ('UR', 'Eastman School of Music', 'UofR Eastman', 'eastman');
+
+-- Drivers for BOTH serve every campus
+INSERT INTO driver_campuses (event_id, driver_id, leg, campus)
+SELECT d.event_id, d.driver_id, d.leg, c.code
+FROM drivers d
+    INNER JOIN campuses c ON c.code = d.campus OR d.campus = 'BOTH';
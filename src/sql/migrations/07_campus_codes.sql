@@ -0,0 +1,18 @@
+-- Campus codes are stored trimmed and uppercase
+UPDATE OR IGNORE campuses SET code = UPPER(TRIM(code));
+DELETE FROM campuses WHERE code != UPPER(TRIM(code));
+
+UPDATE pickup_points SET campus = UPPER(TRIM(campus));
+UPDATE driver_campuses SET campus = UPPER(TRIM(campus));
+UPDATE rides SET campus = UPPER(TRIM(campus));
+UPDATE drivers SET campus = UPPER(TRIM(campus));
+
+-- The pickup form sent "UofR" while rows were meant to store "UR"
+UPDATE pickup_points SET campus = 'UR' WHERE campus = 'UOFR';
+UPDATE driver_campuses SET campus = 'UR' WHERE campus = 'UOFR';
+UPDATE rides SET campus = 'UR' WHERE campus = 'UOFR';
+UPDATE drivers SET campus = 'UR' WHERE campus = 'UOFR';
+
+-- Unknown pickup campuses were stored as "BOTH", a rider can't be picked up
+-- from every campus so these rides could never be matched
+DELETE FROM rides WHERE campus = 'BOTH' OR campus IS NULL OR campus = '';
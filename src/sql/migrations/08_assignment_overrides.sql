@@ -0,0 +1,14 @@
+ALTER TABLE rides ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS assignment_changes (
+    event_id TEXT,
+    rider_id TEXT,
+    leg TEXT,
+    driver_id TEXT,
+    pinned INTEGER,
+    changed_by TEXT,
+    change_time INTEGER,
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE,
+    FOREIGN KEY (rider_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (changed_by) REFERENCES users (id)
+);
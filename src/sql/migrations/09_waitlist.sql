@@ -0,0 +1,13 @@
+-- Rides that already exist keep their order through rowid
+ALTER TABLE rides ADD COLUMN request_time INTEGER NOT NULL DEFAULT 0;
+UPDATE rides SET request_time = CAST(strftime('%s', 'now', 'localtime') AS INTEGER);
+
+-- Rides on events that already passed, events are deleted once they pass
+ALTER TABLE users ADD COLUMN rides_taken INTEGER NOT NULL DEFAULT 0;
+UPDATE users SET rides_taken = (
+    SELECT COUNT(DISTINCT rides.event_id)
+    FROM rides
+    WHERE rides.rider_id = users.id
+        AND rides.driver_id IS NOT NULL
+        AND rides.event_id NOT IN (SELECT id FROM events)
+);
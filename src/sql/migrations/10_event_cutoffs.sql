@@ -0,0 +1,2 @@
+ALTER TABLE events ADD COLUMN signup_deadline INTEGER;
+ALTER TABLE events ADD COLUMN freeze_time INTEGER;
@@ -0,0 +1,2 @@
+ALTER TABLE events ADD COLUMN cancelled_at INTEGER;
+ALTER TABLE events ADD COLUMN cancelled_by TEXT;
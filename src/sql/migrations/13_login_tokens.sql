@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS login_tokens (
+    user_id TEXT,
+    token_id TEXT,
+    request_time INTEGER,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
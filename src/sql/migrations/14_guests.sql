@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN guest_token TEXT;
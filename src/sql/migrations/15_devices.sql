@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS devices (
+    id TEXT PRIMARY KEY,
+    user_id TEXT,
+    token TEXT,
+    name TEXT,
+    created_at INTEGER,
+    last_seen INTEGER,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS invites (
+    id TEXT PRIMARY KEY,
+    created_by TEXT,
+    email TEXT,
+    created_at INTEGER,
+    expires_at INTEGER,
+    max_uses INTEGER,
+    uses INTEGER NOT NULL DEFAULT 0,
+    revoked INTEGER NOT NULL DEFAULT 0,
+    FOREIGN KEY (created_by) REFERENCES users (id)
+);
+
+ALTER TABLE users ADD COLUMN invite_id TEXT;
+ALTER TABLE users ADD COLUMN invited_by TEXT;
@@ -0,0 +1,115 @@
+UPDATE users SET email = TRIM(email) WHERE email IS NOT NULL;
+
+-- Merge accounts that share an email into the oldest one,
+-- which is the one logins already found first
+CREATE TEMP TABLE email_merges AS
+SELECT
+    u.id AS old_id,
+    (
+        SELECT k.id
+        FROM users k
+        WHERE k.email = u.email COLLATE NOCASE
+        ORDER BY k.rowid
+        LIMIT 1
+    ) AS new_id
+FROM users u
+WHERE u.email IS NOT NULL;
+
+DELETE FROM email_merges WHERE old_id = new_id;
+
+-- Drop sign ups the kept account already has for the same event and leg
+DELETE FROM rides
+WHERE rider_id IN (SELECT old_id FROM email_merges)
+    AND EXISTS (
+        SELECT 1 FROM rides kept
+        WHERE kept.rider_id = (SELECT new_id FROM email_merges WHERE old_id = rides.rider_id)
+            AND kept.event_id = rides.event_id
+            AND kept.leg = rides.leg
+    );
+
+DELETE FROM driver_campuses
+WHERE driver_id IN (SELECT old_id FROM email_merges)
+    AND EXISTS (
+        SELECT 1 FROM drivers kept
+        WHERE kept.driver_id = (SELECT new_id FROM email_merges WHERE old_id = driver_campuses.driver_id)
+            AND kept.event_id = driver_campuses.event_id
+            AND kept.leg = driver_campuses.leg
+    );
+
+-- Passengers of a drive that is dropped lose their seat and are matched again,
+-- the kept account's car may not have room for them
+UPDATE rides
+SET driver_id = NULL, driver_cancelled = 1
+WHERE driver_id IN (SELECT old_id FROM email_merges)
+    AND EXISTS (
+        SELECT 1 FROM drivers kept
+        WHERE kept.driver_id = (SELECT new_id FROM email_merges WHERE old_id = rides.driver_id)
+            AND kept.event_id = rides.event_id
+            AND kept.leg = rides.leg
+    );
+
+DELETE FROM drivers
+WHERE driver_id IN (SELECT old_id FROM email_merges)
+    AND EXISTS (
+        SELECT 1 FROM drivers kept
+        WHERE kept.driver_id = (SELECT new_id FROM email_merges WHERE old_id = drivers.driver_id)
+            AND kept.event_id = drivers.event_id
+            AND kept.leg = drivers.leg
+    );
+
+UPDATE rides SET rider_id = (SELECT new_id FROM email_merges WHERE old_id = rider_id)
+WHERE rider_id IN (SELECT old_id FROM email_merges);
+UPDATE rides SET driver_id = (SELECT new_id FROM email_merges WHERE old_id = driver_id)
+WHERE driver_id IN (SELECT old_id FROM email_merges);
+UPDATE drivers SET driver_id = (SELECT new_id FROM email_merges WHERE old_id = driver_id)
+WHERE driver_id IN (SELECT old_id FROM email_merges);
+UPDATE driver_campuses SET driver_id = (SELECT new_id FROM email_merges WHERE old_id = driver_id)
+WHERE driver_id IN (SELECT old_id FROM email_merges);
+UPDATE vehicles SET user_id = (SELECT new_id FROM email_merges WHERE old_id = user_id)
+WHERE user_id IN (SELECT old_id FROM email_merges);
+UPDATE events SET creator_id = (SELECT new_id FROM email_merges WHERE old_id = creator_id)
+WHERE creator_id IN (SELECT old_id FROM email_merges);
+UPDATE events SET cancelled_by = (SELECT new_id FROM email_merges WHERE old_id = cancelled_by)
+WHERE cancelled_by IN (SELECT old_id FROM email_merges);
+UPDATE assignment_changes SET rider_id = (SELECT new_id FROM email_merges WHERE old_id = rider_id)
+WHERE rider_id IN (SELECT old_id FROM email_merges);
+UPDATE assignment_changes SET driver_id = (SELECT new_id FROM email_merges WHERE old_id = driver_id)
+WHERE driver_id IN (SELECT old_id FROM email_merges);
+UPDATE assignment_changes SET changed_by = (SELECT new_id FROM email_merges WHERE old_id = changed_by)
+WHERE changed_by IN (SELECT old_id FROM email_merges);
+UPDATE invites SET created_by = (SELECT new_id FROM email_merges WHERE old_id = created_by)
+WHERE created_by IN (SELECT old_id FROM email_merges);
+UPDATE users SET invited_by = (SELECT new_id FROM email_merges WHERE old_id = invited_by)
+WHERE invited_by IN (SELECT old_id FROM email_merges);
+UPDATE devices SET user_id = (SELECT new_id FROM email_merges WHERE old_id = user_id)
+WHERE user_id IN (SELECT old_id FROM email_merges);
+
+-- Old reset and login links would log into the merged account, make the user ask again
+DELETE FROM resets WHERE user_id IN (SELECT old_id FROM email_merges);
+DELETE FROM login_tokens WHERE user_id IN (SELECT old_id FROM email_merges);
+
+UPDATE users
+SET rides_taken = rides_taken + (
+    SELECT COALESCE(SUM(old.rides_taken), 0)
+    FROM users old
+        INNER JOIN email_merges m ON m.old_id = old.id
+    WHERE m.new_id = users.id
+)
+WHERE id IN (SELECT new_id FROM email_merges);
+
+DELETE FROM users WHERE id IN (SELECT old_id FROM email_merges);
+
+DROP TABLE email_merges;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email ON users (email COLLATE NOCASE);
+
+-- Accounts made before verification existed are trusted
+ALTER TABLE users ADD COLUMN verified INTEGER NOT NULL DEFAULT 0;
+UPDATE users SET verified = 1;
+
+CREATE TABLE IF NOT EXISTS verifications (
+    user_id TEXT,
+    token_id TEXT,
+    request_time INTEGER,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
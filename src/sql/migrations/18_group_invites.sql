@@ -0,0 +1,13 @@
+-- Riders only join a group once they accept the invite
+CREATE TABLE IF NOT EXISTS group_invites (
+    id TEXT PRIMARY KEY,
+    event_id TEXT,
+    leg TEXT,
+    group_id TEXT,
+    invited_by TEXT,
+    user_id TEXT,
+    created_at INTEGER,
+    FOREIGN KEY (event_id) REFERENCES events (id) ON DELETE CASCADE,
+    FOREIGN KEY (invited_by) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
@@ -0,0 +1,14 @@
+-- Drop repeat sign ups for the same event and leg, keeping the ride
+-- that already has a driver, otherwise the first one asked for
+DELETE FROM rides
+WHERE rowid != (
+    SELECT kept.rowid
+    FROM rides kept
+    WHERE kept.event_id = rides.event_id
+        AND kept.rider_id = rides.rider_id
+        AND kept.leg = rides.leg
+    ORDER BY kept.driver_id IS NULL, kept.rowid
+    LIMIT 1
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS rides_event_rider_leg ON rides (event_id, rider_id, leg);
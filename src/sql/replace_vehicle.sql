@@ -0,0 +1,3 @@
+UPDATE drivers
+SET vehicle_id = ?
+WHERE vehicle_id = ?;
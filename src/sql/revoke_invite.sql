@@ -0,0 +1,3 @@
+UPDATE invites
+SET revoked = 1
+WHERE id = ?;
@@ -0,0 +1,7 @@
+UPDATE rides
+SET driver_id = ?,
+	pinned = ?,
+	driver_cancelled = 0
+WHERE event_id = ?
+	AND rider_id = ?
+	AND leg = ?;
@@ -0,0 +1,3 @@
+UPDATE users
+SET role = ?
+WHERE id = ?;
@@ -0,0 +1,4 @@
+UPDATE users
+SET invite_id = ?1,
+    invited_by = (SELECT created_by FROM invites WHERE id = ?1)
+WHERE id = ?2;
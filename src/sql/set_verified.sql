@@ -0,0 +1,3 @@
+UPDATE users
+SET verified = 1
+WHERE id = ?;
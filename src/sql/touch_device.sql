@@ -0,0 +1,3 @@
+UPDATE devices
+SET last_seen = ?
+WHERE id = ?;
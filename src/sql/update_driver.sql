@@ -0,0 +1,6 @@
+UPDATE drivers
+SET vehicle_id = ?,
+    seats = ?
+WHERE event_id = ?
+    AND driver_id = ?
+    AND leg = ?;
@@ -6,5 +6,8 @@ SET
     address2=?,
     city=?,
     state=?,
-    zipcode=?
+    zipcode=?,
+    strategy=?,
+    signup_deadline=?,
+    freeze_time=?
 WHERE id = ?;
@@ -0,0 +1,5 @@
+UPDATE rides
+SET pickup_location = ?,
+    pickup_point = ?
+WHERE event_id = ?
+    AND rider_id = ?;
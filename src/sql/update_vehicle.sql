@@ -0,0 +1,6 @@
+UPDATE vehicles
+SET
+    color=?,
+    make=?,
+    model=?
+WHERE id = ?;
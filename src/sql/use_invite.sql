@@ -0,0 +1,6 @@
+UPDATE invites
+SET uses = uses + 1
+WHERE id = ?1
+    AND revoked = 0
+    AND (expires_at IS NULL OR expires_at > ?2)
+    AND (max_uses IS NULL OR uses < max_uses);
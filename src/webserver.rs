@@ -1,45 +1,30 @@
 use crate::db;
-use crate::models::{Campus, Event, EventData, Vehicle, EventInfo};
-use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
-use actix_web::cookie::Key;
+use crate::matching::{self, Assignment};
+use crate::models::{Campus, CampusCode, Device, Driver, Event, EventData, GroupInvite, Invite, Leg, ResetRequest, Ride, Role, User, Vehicle, EventInfo};
+use actix_session::{storage::CookieSessionStore, Session, SessionExt, SessionMiddleware};
+use actix_web::cookie::{time, Cookie, Key};
+use actix_web::dev::{Service, ServiceRequest};
 use actix_web::middleware::Logger;
-use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{get, post, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use askama::Template;
-use chrono::NaiveDateTime;
+use chrono::{Local, NaiveDateTime};
 use log::info;
 use serde::Deserialize;
 use std::env;
 use std::fs::File;
 use std::io::Read;
 use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::{self as queue, UnboundedSender};
 use uuid::Uuid;
 
-use lazy_static::lazy_static;
-
-use crate::email::send_reset_email;
-
-// Secret Invite ID, loaded from environment variables
-lazy_static! {
-    static ref INVITE_ID: Uuid = {
-        env::vars()
-            .filter_map(|(key, val)| {
-                if key == "INVITE_ID" {
-                    if let Ok(id) = Uuid::parse_str(&val) {
-                        Some(id)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
-            .next()
-            .unwrap_or(Uuid::new_v4())
-    };
-}
+use crate::email::{send_event_cancelled, send_event_updates, send_leadership_alert, send_login_email, send_reset_email, send_verification_email, EventUpdate};
 
 struct AppState {
-    tx: Sender<()>
+    tx: Sender<()>,
+    /// Emails about changed events, sent in the background
+    updates: UnboundedSender<EventUpdate>,
+    /// Set by ADMIN_EMAIL, this user is made an admin once they verify it
+    admin_email: Option<String>
 }
 
 // Templates
@@ -49,9 +34,20 @@ struct LoginTemplate {
     error: String,
 }
 
+#[derive(Template)]
+#[template(path = "groups.html")]
+struct GroupInvitesTemplate {
+    /// Each invite with its event's name and who sent it
+    invites: Vec<(GroupInvite, String, String)>,
+    error: String,
+}
+
 #[derive(Template)]
 #[template(path = "summary.html")]
-struct SummaryTemplate {}
+struct SummaryTemplate {
+    role: Role,
+    verified: bool,
+}
 
 #[derive(Template)]
 #[template(path = "upcoming_events.html")]
@@ -68,11 +64,30 @@ struct EventsTemplate {
 
 #[derive(Template)]
 #[template(path = "pickup.html")]
-struct PickupTemplate {}
+struct PickupTemplate {
+    campuses: Vec<Campus>,
+    error: String,
+}
 
 #[derive(Template)]
 #[template(path = "manage_events.html")]
-struct ManageEventsTemplate {}
+struct ManageEventsTemplate {
+    /// Events the user can edit
+    events: Vec<Event>,
+    /// Called off events the user could have edited, with who cancelled them
+    cancelled: Vec<(Event, String)>,
+    error: String,
+}
+
+#[derive(Template)]
+#[template(path = "edit_event.html")]
+struct EditEventTemplate {
+    event: Event,
+    /// Cutoffs formatted for datetime-local inputs, empty if unset
+    signup_deadline: String,
+    freeze_time: String,
+    error: String,
+}
 
 #[derive(Template)]
 #[template(path = "reset_password.html")]
@@ -81,6 +96,18 @@ struct ResetPasswordTemplate {}
 #[derive(Template)]
 #[template(path = "signup.html")]
 struct SignupTemplate {
+    invite_id: Uuid,
+    /// Email the invite is for, if any
+    email: String,
+    error: String,
+}
+
+#[derive(Template)]
+#[template(path = "invites.html")]
+struct InvitesTemplate {
+    /// Invites with the name of who made them
+    invites: Vec<(Invite, String)>,
+    now: NaiveDateTime,
     error: String,
 }
 
@@ -90,9 +117,76 @@ struct VehiclesTemplate {
     vehicles: Vec<Vehicle>,
 }
 
+#[derive(Template)]
+#[template(path = "edit_vehicle.html")]
+struct EditVehicleTemplate {
+    vehicle: Vehicle,
+    /// Number of upcoming drives using the vehicle
+    drives: usize,
+    /// The user's other vehicles, which can take over those drives
+    others: Vec<Vehicle>,
+    error: String,
+}
+
+#[derive(Template)]
+#[template(path = "users.html")]
+struct UsersTemplate {
+    /// Users with the name of who invited them
+    users: Vec<(User, String)>,
+    user_id: Uuid,
+    roles: [Role; 3],
+}
+
+#[derive(Template)]
+#[template(path = "devices.html")]
+struct DevicesTemplate {
+    devices: Vec<Device>,
+    /// The device the page is being viewed on, if it is remembered
+    current: Option<Uuid>,
+}
+
+#[derive(Template)]
+#[template(path = "guest_ride.html")]
+struct GuestRideTemplate {
+    event: Event,
+    campuses: Vec<Campus>,
+    error: String,
+}
+
+#[derive(Template)]
+#[template(path = "guest_status.html")]
+struct GuestStatusTemplate {
+    guest: User,
+    events_data: Vec<EventData>,
+}
+
+#[derive(Template)]
+#[template(path = "edit_ride.html")]
+struct EditRideTemplate {
+    event: Event,
+    campuses: Vec<Campus>,
+    ride: Ride,
+    error: String,
+}
+
+#[derive(Template)]
+#[template(path = "edit_drive.html")]
+struct EditDriveTemplate {
+    event: Event,
+    vehicles: Vec<Vehicle>,
+    /// Vehicle the user is driving
+    vehicle_id: Uuid,
+    /// Each leg the user drives with the code its seats field is named by
+    drivers: Vec<(Driver, &'static str)>,
+    error: String,
+}
+
 #[derive(Template)]
 #[template(path = "seats.html")]
-struct SeatsTemplate {}
+struct SeatsTemplate {
+    campuses: Vec<Campus>,
+    error: String,
+}
 
 #[derive(Template)]
 #[template(path = "info.html")]
@@ -100,13 +194,94 @@ struct EventsInfoTemplate {
     events: Vec<EventInfo>
 }
 
+/// A proposed assignment with names ready to show
+struct PreviewRow {
+    event: String,
+    leg: &'static str,
+    rider: String,
+    driver: String,
+}
+
+#[derive(Template)]
+#[template(path = "match_preview.html")]
+struct MatchPreviewTemplate {
+    rows: Vec<PreviewRow>,
+    /// The previewed assignments, sent back when applying
+    plan: String,
+    message: String,
+}
+
+/// A ride with names ready to show
+struct RideRow {
+    rider: String,
+    /// Stored form of the ride's leg, sent back when changing it
+    leg: &'static str,
+    ride: Ride,
+    driver: Option<String>,
+}
+
+/// A driver with their name and how many seats are taken
+struct DriverRow {
+    name: String,
+    driver: Driver,
+    taken: i64,
+}
+
+/// A manual assignment change with names ready to show
+struct ChangeRow {
+    time: NaiveDateTime,
+    rider: String,
+    leg: &'static str,
+    driver: Option<String>,
+    pinned: bool,
+    changed_by: String,
+}
+
+#[derive(Template)]
+#[template(path = "assignments.html")]
+struct AssignmentsTemplate {
+    event: Event,
+    rides: Vec<RideRow>,
+    drivers: Vec<DriverRow>,
+    changes: Vec<ChangeRow>,
+    error: String,
+}
+
+#[derive(Template)]
+#[template(path = "campuses.html")]
+struct CampusesTemplate {
+    campuses: Vec<Campus>,
+    error: String,
+}
+
 #[derive(Template)]
 #[template(path = "check_email.html")]
-struct CheckEmailTemplate {}
+struct CheckEmailTemplate {
+    heading: &'static str,
+    message: &'static str,
+}
+
+#[derive(Template)]
+#[template(path = "login_link.html")]
+struct LoginLinkTemplate {}
+
+#[derive(Template)]
+#[template(path = "login_with_link.html")]
+struct LoginWithLinkTemplate {
+    token_id: String,
+}
 
 #[derive(Template)]
 #[template(path = "set_password.html")]
-struct SetPasswordTemplate {}
+struct SetPasswordTemplate {
+    error: String,
+}
+
+#[derive(Template)]
+#[template(path = "reset_error.html")]
+struct ResetErrorTemplate {
+    message: &'static str,
+}
 
 
 macro_rules! auth {
@@ -120,6 +295,135 @@ macro_rules! auth {
     };
 }
 
+/// Like `auth!`, but also sends users without at least the given role home
+macro_rules! role {
+    ($s:ident, $role:expr) => {
+        auth!($s);
+        if session_role(&$s) < $role {
+            return HttpResponse::SeeOther()
+                .append_header(("Location", "/"))
+                .finish();
+        }
+    };
+}
+
+/// Like `auth!`, but also sends users who haven't verified their email home
+macro_rules! verified {
+    ($s:ident) => {
+        auth!($s);
+        if !session_verified(&$s) {
+            return HttpResponse::SeeOther()
+                .append_header(("Location", "/"))
+                .finish();
+        }
+    };
+}
+
+/// Get the role of the logged in user.
+/// Roles are read from the database every time so changes apply right away
+fn session_role(s: &Session) -> Role {
+    s.get::<String>("user_id").unwrap()
+        .and_then(|id| Uuid::parse_str(&id).ok())
+        .and_then(|id| db::get_user(&db::connect(), id).unwrap())
+        .map(|user| user.role)
+        .unwrap_or(Role::Member)
+}
+
+/// Whether the logged in user has verified their email
+fn session_verified(s: &Session) -> bool {
+    s.get::<String>("user_id").unwrap()
+        .and_then(|id| Uuid::parse_str(&id).ok())
+        .and_then(|id| db::get_user(&db::connect(), id).unwrap())
+        .is_some_and(|user| user.verified)
+}
+
+/// Cookie that keeps a remembered device logged in
+const DEVICE_COOKIE: &str = "device";
+
+/// Log back in remembered devices whose session ended,
+/// and log out devices that have been revoked
+fn check_device(req: &ServiceRequest) {
+    let s = req.get_session();
+    let device_id = s.get::<String>("device_id").unwrap();
+    let logged_in = s.get::<bool>("logged_in").unwrap().unwrap_or(false);
+    let token = req.cookie(DEVICE_COOKIE)
+        .and_then(|cookie| Uuid::parse_str(cookie.value()).ok());
+
+    if let Some(id) = device_id {
+        let conn = db::connect();
+        let device = Uuid::parse_str(&id).ok()
+            .and_then(|id| db::get_device(&conn, id).unwrap());
+
+        if device.is_none() {
+            s.purge();
+        }
+    } else if let Some(token) = token.filter(|_| !logged_in) {
+        let conn = db::connect();
+
+        if let Some(device) = db::get_device_by_token(&conn, token).unwrap() {
+            db::touch_device(&conn, device.id).unwrap();
+
+            s.insert("logged_in", true).unwrap();
+            s.insert("user_id", device.user_id.to_string()).unwrap();
+            s.insert("device_id", device.id.to_string()).unwrap();
+        }
+    }
+}
+
+/// Log a user in and send them home.
+/// If they asked to, the device is remembered so they stay logged in
+fn log_in(s: &Session, req: &HttpRequest, conn: &sqlite::Connection, user: &User, remember: bool) -> HttpResponse {
+    let admin_email = req.app_data::<web::Data<AppState>>()
+        .and_then(|state| state.admin_email.as_deref());
+    promote_configured_admin(conn, user, admin_email);
+
+    s.renew();
+    s.insert("logged_in", true).unwrap();
+    s.insert("user_id", user.id.to_string()).unwrap();
+
+    let mut response = HttpResponse::SeeOther();
+    response.append_header(("Location", "/"));
+
+    if remember {
+        let name: String = req.headers().get("User-Agent")
+            .and_then(|agent| agent.to_str().ok())
+            .unwrap_or("Unknown Browser")
+            .chars()
+            .take(200)
+            .collect();
+
+        let (id, token) = db::create_device(conn, user.id, &name).unwrap();
+        s.insert("device_id", id.to_string()).unwrap();
+
+        response.cookie(
+            Cookie::build(DEVICE_COOKIE, token.to_string())
+                .path("/")
+                .secure(true)
+                .http_only(true)
+                .max_age(time::Duration::days(Device::lifetime().num_days()))
+                .finish()
+        );
+    }
+
+    response.finish()
+}
+
+/// Key used to sign session cookies, set by the SESSION_KEY environment variable
+/// so sessions survive restarts. Falls back to a new key if unset or too short
+fn session_key() -> Key {
+    match env::var("SESSION_KEY") {
+        Ok(key) if key.len() >= 64 => Key::from(key.as_bytes()),
+        Ok(_) => {
+            log::warn!("SESSION_KEY must be at least 64 characters, everyone will be logged out on restart");
+            Key::generate()
+        }
+        Err(_) => {
+            log::warn!("SESSION_KEY is not set, everyone will be logged out on restart");
+            Key::generate()
+        }
+    }
+}
+
 #[get("/css")]
 async fn get_css() -> impl Responder {
     let mut f = File::open("./public/style.css").unwrap();
@@ -141,7 +445,14 @@ async fn get_upcoming_events_js() -> impl Responder {
 #[get("/")]
 async fn get_root(s: Session) -> impl Responder {
     auth!(s);
-    HttpResponse::Ok().body(SummaryTemplate {}.render().unwrap())
+    HttpResponse::Ok().body(
+        SummaryTemplate {
+            role: session_role(&s),
+            verified: session_verified(&s),
+        }
+        .render()
+        .unwrap()
+    )
 }
 
 #[get("/upcoming_events")]
@@ -152,7 +463,7 @@ async fn get_upcoming_events(s: Session) -> impl Responder {
     let id = Uuid::parse_str(id.as_str()).unwrap();
 
     let conn = db::connect();
-    let events_data = db::get_events_data(&conn, id).unwrap();
+    let events_data = db::get_events_data(&conn, id, matching::configured_priority()).unwrap();
 
     HttpResponse::Ok().body(UpcomingEventsTemplate { events_data }.render().unwrap())
 }
@@ -166,22 +477,19 @@ async fn get_login() -> impl Responder {
 struct LoginFormData {
     email: String,
     password: String,
+    /// Set when the user wants to stay logged in on this device
+    remember: Option<String>,
 }
 
 #[post("/login")]
-async fn post_login(s: Session, form: web::Form<LoginFormData>) -> impl Responder {
+async fn post_login(s: Session, req: HttpRequest, form: web::Form<LoginFormData>) -> impl Responder {
     let conn = db::connect();
     let user = db::get_user_by_email(&conn, form.email.clone()).unwrap();
 
     if let Some(u) = user {
         let verify = bcrypt::verify(form.password.clone(), u.password.as_str());
         if verify.is_ok() && verify.unwrap() {
-            s.insert("logged_in", true).unwrap();
-            s.insert("user_id", u.id.to_string()).unwrap();
-
-            return HttpResponse::SeeOther()
-                .append_header(("Location", "/"))
-                .finish();
+            return log_in(&s, &req, &conn, &u, form.remember.is_some());
         }
     }
 
@@ -194,6 +502,71 @@ async fn post_login(s: Session, form: web::Form<LoginFormData>) -> impl Responde
     )
 }
 
+#[get("/login/link")]
+async fn get_login_link() -> impl Responder {
+    LoginLinkTemplate {}
+}
+
+#[derive(Deserialize)]
+struct LoginLinkForm {
+    email: String,
+}
+
+#[post("/login/link")]
+async fn post_login_link(form: web::Form<LoginLinkForm>) -> impl Responder {
+    let conn = db::connect();
+
+    if let Ok(Some(user)) = db::get_user_by_email(&conn, form.email.clone()) {
+        let id = db::create_login_token(&conn, user.id).unwrap();
+        send_login_email(&user.email, &id.to_string()).await.ok();
+    }
+
+    CheckEmailTemplate {
+        heading: "Email Me a Login Link",
+        message: "Check your email for a link to log in, it works once and expires in 15 minutes",
+    }
+}
+
+/// Opening the link only shows a button, so email scanners that follow
+/// links don't use it up before the user does
+#[get("/login/link/{id}")]
+async fn get_login_with_link(path: web::Path<(String,)>) -> impl Responder {
+    LoginWithLinkTemplate {
+        token_id: path.0.clone(),
+    }
+}
+
+#[derive(Deserialize)]
+struct LoginWithLinkForm {
+    /// Set when the user wants to stay logged in on this device
+    remember: Option<String>,
+}
+
+#[post("/login/link/{id}")]
+async fn post_login_with_link(s: Session, req: HttpRequest, path: web::Path<(String,)>, form: web::Form<LoginWithLinkForm>) -> impl Responder {
+    let conn = db::connect();
+
+    let user = Uuid::parse_str(&path.0).ok()
+        .and_then(|id| db::redeem_login_token(&conn, id).unwrap())
+        .and_then(|user_id| db::get_user(&conn, user_id).unwrap());
+
+    match user {
+        Some(mut user) => {
+            // The link was sent to their email, so they own it
+            db::set_verified(&conn, user.id).unwrap();
+            user.verified = true;
+            log_in(&s, &req, &conn, &user, form.remember.is_some())
+        }
+        None => HttpResponse::Ok().body(
+            LoginTemplate {
+                error: "That login link has expired or was already used".into(),
+            }
+            .render()
+            .unwrap(),
+        )
+    }
+}
+
 #[derive(Deserialize)]
 struct FlowQuery {
     flow: String,
@@ -204,8 +577,9 @@ async fn get_events(s: Session, flow: web::Query<FlowQuery>) -> impl Responder {
     auth!(s);
 
     let href = match flow.flow.as_str() {
-        "drive" => "/vehicles",
-        "ride" => "/pickup",
+        "drive" if session_verified(&s) => "/vehicles",
+        "ride" if session_verified(&s) => "/pickup",
+        "assign" if session_role(&s) >= Role::Organizer => "/assignments",
         _ => {
             return HttpResponse::SeeOther()
                 .append_header(("Location", "/"))
@@ -222,24 +596,68 @@ async fn get_events(s: Session, flow: web::Query<FlowQuery>) -> impl Responder {
     HttpResponse::Ok().body(EventsTemplate { events, href }.render().unwrap())
 }
 
+/// Message for riders and drivers trying to sign up after an event's deadline
+fn signups_closed_error(conn: &sqlite::Connection, event_id: Uuid) -> Option<String> {
+    let event = db::get_event(conn, event_id).unwrap()?;
+
+    if event.is_cancelled() {
+        return Some(format!("Sorry, {} has been cancelled.", event.name));
+    }
+
+    if !event.signups_closed(Local::now().naive_local()) {
+        return None;
+    }
+
+    Some(format!(
+        "Sorry, sign ups for {} closed {}. Reach out to leadership if you still need a ride.",
+        event.name,
+        event.signup_deadline.unwrap().format("%A, %B %d at %l:%M %p")
+    ))
+}
+
 #[get("/pickup")]
 async fn get_pickup(s: Session, q: web::Query<EventQuery>) -> impl Responder {
-    auth!(s);
+    verified!(s);
 
     s.insert("event_id", q.event_id.clone()).unwrap();
 
-    HttpResponse::Ok().body(PickupTemplate {}.render().unwrap())
+    let conn = db::connect();
+    let campuses = db::get_campuses(&conn).unwrap();
+
+    HttpResponse::Ok().body(
+        PickupTemplate {
+            campuses,
+            error: Uuid::parse_str(&q.event_id).ok()
+                .and_then(|event_id| signups_closed_error(&conn, event_id))
+                .unwrap_or_default(),
+        }
+        .render()
+        .unwrap(),
+    )
 }
 
 #[derive(Deserialize)]
 struct PickupData {
     campus: String,
     pickup: String,
+    /// Emails of other riders who must share the car, comma separated
+    group: Option<String>,
+    legs: String,
+}
+
+/// Parse which legs of a trip a rider or driver signed up for
+fn parse_legs(legs: &str) -> Option<Vec<Leg>> {
+    match legs {
+        "BOTH" => Some(vec![Leg::To, Leg::Return]),
+        "TO" => Some(vec![Leg::To]),
+        "RETURN" => Some(vec![Leg::Return]),
+        _ => None
+    }
 }
 
 #[post("/pickup")]
 async fn post_pickup(s: Session, form: web::Form<PickupData>, state: web::Data<AppState>) -> impl Responder {
-    auth!(s);
+    verified!(s);
 
     let id: String = s.get("user_id").unwrap().unwrap();
     let id = Uuid::parse_str(id.as_str()).unwrap();
@@ -247,11 +665,106 @@ async fn post_pickup(s: Session, form: web::Form<PickupData>, state: web::Data<A
     let event_id: String = s.get("event_id").unwrap().unwrap();
     let event_id = Uuid::parse_str(event_id.as_str()).unwrap();
 
-    let campus: Campus = form.campus.as_str().into();
     let pickup = form.pickup.clone();
 
     let conn = db::connect();
-    db::create_ride(&conn, id, event_id, campus, pickup).unwrap();
+
+    if let Some(error) = signups_closed_error(&conn, event_id) {
+        return HttpResponse::Ok().body(
+            PickupTemplate {
+                campuses: db::get_campuses(&conn).unwrap(),
+                error,
+            }
+            .render()
+            .unwrap(),
+        );
+    }
+
+    let campus = form.campus.parse::<CampusCode>().ok()
+        .and_then(|code| db::get_campus(&conn, &code).unwrap());
+
+    let campus = match campus {
+        Some(campus) => campus,
+        None => {
+            return HttpResponse::Ok().body(
+                PickupTemplate {
+                    campuses: db::get_campuses(&conn).unwrap(),
+                    error: "Select a campus".into(),
+                }
+                .render()
+                .unwrap(),
+            );
+        }
+    };
+
+    // Riders edit rides they already have instead of asking again
+    let legs: Vec<Leg> = match parse_legs(&form.legs) {
+        Some(legs) => legs.into_iter()
+            .filter(|&leg| db::get_ride(&conn, event_id, id, leg).unwrap().is_none())
+            .collect(),
+        None => {
+            return HttpResponse::Ok().body(
+                PickupTemplate {
+                    campuses: db::get_campuses(&conn).unwrap(),
+                    error: "Select which way you need a ride".into(),
+                }
+                .render()
+                .unwrap(),
+            );
+        }
+    };
+
+    if legs.is_empty() {
+        return HttpResponse::Ok().body(
+            PickupTemplate {
+                campuses: db::get_campuses(&conn).unwrap(),
+                error: "You already asked for a ride to this event, change it from your upcoming rides".into(),
+            }
+            .render()
+            .unwrap(),
+        );
+    }
+
+    let mut members = Vec::new();
+    let emails = form.group.as_deref().unwrap_or("").split(',')
+        .map(|email| email.trim())
+        .filter(|email| !email.is_empty());
+
+    for email in emails {
+        let error = match db::get_user_by_email(&conn, email.to_string()).unwrap() {
+            None => Some(format!("No account found for {email}")),
+            Some(user) if db::get_driver_events(&conn, user.id).unwrap()
+                .iter()
+                .any(|event| event.id == event_id) => {
+                Some(format!("{} is driving to this event", user.fullname))
+            }
+            Some(user) => {
+                if user.id != id && !members.contains(&user.id) {
+                    members.push(user.id);
+                }
+                None
+            }
+        };
+
+        if let Some(error) = error {
+            return HttpResponse::Ok().body(
+                PickupTemplate {
+                    campuses: db::get_campuses(&conn).unwrap(),
+                    error,
+                }
+                .render()
+                .unwrap(),
+            );
+        }
+    }
+
+    for leg in legs {
+        if members.is_empty() {
+            db::create_ride(&conn, id, event_id, &campus, pickup.clone(), None, leg).unwrap();
+        } else {
+            db::create_group_ride(&conn, id, event_id, &campus, pickup.clone(), &members, leg).unwrap();
+        }
+    }
 
     // Notify worker thread
     state.tx.send(()).unwrap();
@@ -261,323 +774,1940 @@ async fn post_pickup(s: Session, form: web::Form<PickupData>, state: web::Data<A
         .finish()
 }
 
-#[derive(Deserialize)]
-struct EventQuery {
-    event_id: String,
+/// Render the logged in user's group invites
+fn render_group_invites(conn: &sqlite::Connection, user_id: Uuid, error: &str) -> HttpResponse {
+    let invites = db::get_user_group_invites(conn, user_id).unwrap()
+        .into_iter()
+        .filter_map(|invite| {
+            let event = db::get_event(conn, invite.event_id).unwrap()?;
+            let inviter = db::get_user(conn, invite.invited_by).unwrap()?;
+            Some((invite, event.name, inviter.fullname))
+        })
+        .collect();
+
+    HttpResponse::Ok().body(
+        GroupInvitesTemplate {
+            invites,
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
 }
 
-#[get("/vehicles")]
-async fn get_vehicles(s: Session, q: web::Query<EventQuery>) -> impl Responder {
-    auth!(s);
+/// Get a group invite sent to the logged in user
+fn own_group_invite(conn: &sqlite::Connection, invite_id: &str, user_id: Uuid) -> Option<GroupInvite> {
+    Uuid::parse_str(invite_id).ok()
+        .and_then(|id| db::get_group_invite(conn, id).unwrap())
+        .filter(|invite| invite.user_id == user_id)
+}
 
-    s.insert("event_id", q.event_id.clone()).unwrap();
+#[get("/groups")]
+async fn get_group_invites(s: Session) -> impl Responder {
+    auth!(s);
 
     let id: String = s.get("user_id").unwrap().unwrap();
     let id = Uuid::parse_str(id.as_str()).unwrap();
 
-    let conn = db::connect();
-    let vehicles = db::get_driver_vehicles(&conn, id).unwrap();
-
-    HttpResponse::Ok().body(VehiclesTemplate { vehicles }.render().unwrap())
-}
-
-#[derive(Deserialize)]
-struct VehicleFormData {
-    make: String,
-    model: String,
-    color: String,
+    render_group_invites(&db::connect(), id, "")
 }
 
-#[post("/vehicles")]
-async fn post_vehicle(s: Session, form: web::Form<VehicleFormData>) -> impl Responder {
-    auth!(s);
+/// Join the group that sent the invite. Only the accepting user's ride
+/// and the rides of riders already in the group change
+#[post("/groups/{id}/accept")]
+async fn accept_group_invite(s: Session, path: web::Path<(String,)>, state: web::Data<AppState>) -> impl Responder {
+    verified!(s);
 
     let id: String = s.get("user_id").unwrap().unwrap();
     let id = Uuid::parse_str(id.as_str()).unwrap();
 
     let conn = db::connect();
-    db::create_vehicle(
-        &conn,
-        id,
-        form.color.clone(),
-        form.make.clone(),
-        form.model.clone(),
-    )
-    .unwrap();
-    let vehicles = db::get_driver_vehicles(&conn, id).unwrap();
+    let invite = match own_group_invite(&conn, &path.0, id) {
+        Some(invite) => invite,
+        None => {
+            return HttpResponse::SeeOther()
+                .append_header(("Location", "/groups"))
+                .finish()
+        }
+    };
+
+    if let Some(error) = signups_closed_error(&conn, invite.event_id) {
+        return render_group_invites(&conn, id, &error);
+    }
+
+    // The group is gone if whoever asked has left it since
+    let group_ride = db::get_ride(&conn, invite.event_id, invite.invited_by, invite.leg).unwrap()
+        .filter(|ride| ride.group_id == Some(invite.group_id));
+
+    let group_ride = match group_ride {
+        Some(ride) => ride,
+        None => {
+            db::delete_group_invite(&conn, invite.id).unwrap();
+            return render_group_invites(&conn, id, "That group no longer needs a ride");
+        }
+    };
+
+    let driving = db::get_driver_events(&conn, id).unwrap()
+        .iter()
+        .any(|event| event.id == invite.event_id);
+    if driving {
+        return render_group_invites(&conn, id, "You are driving to this event");
+    }
+
+    // Leadership's placements are never undone by members
+    let pinned = db::get_event_rides(&conn, invite.event_id).unwrap()
+        .iter()
+        .filter(|ride| ride.leg == invite.leg)
+        .filter(|ride| ride.rider_id == id || ride.group_id == Some(invite.group_id))
+        .any(|ride| ride.pinned);
+    if pinned {
+        return render_group_invites(&conn, id, "Leadership has placed you or this group in a car, reach out to them to ride together");
+    }
+
+    let campus = db::get_campus(&conn, &group_ride.campus).unwrap().unwrap();
+    db::accept_group_invite(&conn, &invite, &campus, group_ride.pickup_location).unwrap();
+
+    // Notify worker thread
+    state.tx.send(()).unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/"))
+        .finish()
+}
+
+#[post("/groups/{id}/decline")]
+async fn decline_group_invite(s: Session, path: web::Path<(String,)>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    if let Some(invite) = own_group_invite(&conn, &path.0, id) {
+        db::delete_group_invite(&conn, invite.id).unwrap();
+    }
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/groups"))
+        .finish()
+}
+
+#[derive(Deserialize)]
+struct EventQuery {
+    event_id: String,
+}
+
+#[get("/vehicles")]
+async fn get_vehicles(s: Session, q: web::Query<EventQuery>) -> impl Responder {
+    auth!(s);
+
+    s.insert("event_id", q.event_id.clone()).unwrap();
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    let vehicles = db::get_driver_vehicles(&conn, id).unwrap();
+
+    HttpResponse::Ok().body(VehiclesTemplate { vehicles }.render().unwrap())
+}
+
+#[derive(Deserialize)]
+struct VehicleFormData {
+    make: String,
+    model: String,
+    color: String,
+}
+
+#[post("/vehicles")]
+async fn post_vehicle(s: Session, form: web::Form<VehicleFormData>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    db::create_vehicle(
+        &conn,
+        id,
+        form.color.clone(),
+        form.make.clone(),
+        form.model.clone(),
+    )
+    .unwrap();
+    let vehicles = db::get_driver_vehicles(&conn, id).unwrap();
 
     HttpResponse::Ok().body(VehiclesTemplate { vehicles }.render().unwrap())
 }
 
+/// Where to go after changing a vehicle, back to picking one if the user is signing up to drive
+fn vehicles_location(s: &Session) -> String {
+    match s.get::<String>("event_id").unwrap() {
+        Some(event_id) => format!("/vehicles?event_id={event_id}"),
+        None => "/".into()
+    }
+}
+
+/// Get a vehicle by id if it belongs to the user
+fn owned_vehicle(conn: &sqlite::Connection, id: &str, user_id: Uuid) -> Option<Vehicle> {
+    Uuid::parse_str(id).ok()
+        .and_then(|id| db::get_vehicle(conn, id).unwrap())
+        .filter(|vehicle| vehicle.owner_id == user_id)
+}
+
+/// Render the edit form for a vehicle
+fn render_edit_vehicle(conn: &sqlite::Connection, vehicle: Vehicle, error: &str) -> HttpResponse {
+    let drives = db::get_vehicle_drivers(conn, vehicle.id).unwrap().len();
+    let others = db::get_driver_vehicles(conn, vehicle.owner_id).unwrap()
+        .into_iter()
+        .filter(|other| other.id != vehicle.id)
+        .collect();
+
+    HttpResponse::Ok().body(
+        EditVehicleTemplate {
+            vehicle,
+            drives,
+            others,
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+#[get("/vehicles/{id}")]
+async fn get_edit_vehicle(s: Session, path: web::Path<(String,)>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    match owned_vehicle(&conn, &path.0, id) {
+        Some(vehicle) => render_edit_vehicle(&conn, vehicle, ""),
+        None => HttpResponse::SeeOther()
+            .append_header(("Location", vehicles_location(&s)))
+            .finish()
+    }
+}
+
+#[post("/vehicles/{id}")]
+async fn post_edit_vehicle(s: Session, path: web::Path<(String,)>, form: web::Form<VehicleFormData>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    if let Some(vehicle) = owned_vehicle(&conn, &path.0, id) {
+        db::update_vehicle(
+            &conn,
+            vehicle.id,
+            form.color.clone(),
+            form.make.clone(),
+            form.model.clone(),
+        )
+        .unwrap();
+    }
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", vehicles_location(&s)))
+        .finish()
+}
+
+#[derive(Deserialize)]
+struct DeleteVehicleForm {
+    /// Vehicle to use for upcoming drives instead
+    replacement_id: Option<String>,
+}
+
+#[post("/vehicles/{id}/delete")]
+async fn delete_vehicle(s: Session, path: web::Path<(String,)>, form: web::Form<DeleteVehicleForm>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    let vehicle = match owned_vehicle(&conn, &path.0, id) {
+        Some(vehicle) => vehicle,
+        None => {
+            return HttpResponse::SeeOther()
+                .append_header(("Location", vehicles_location(&s)))
+                .finish()
+        }
+    };
+
+    let replacement = form.replacement_id.as_deref()
+        .and_then(|replacement_id| owned_vehicle(&conn, replacement_id, id))
+        .filter(|replacement| replacement.id != vehicle.id);
+
+    // Upcoming drives must keep a vehicle
+    let drives = db::get_vehicle_drivers(&conn, vehicle.id).unwrap();
+    if !drives.is_empty() && replacement.is_none() {
+        return render_edit_vehicle(&conn, vehicle, "Pick another vehicle for your upcoming drives before removing this one");
+    }
+
+    db::delete_vehicle(&conn, vehicle.id, replacement.map(|replacement| replacement.id)).unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", vehicles_location(&s)))
+        .finish()
+}
+
 #[derive(Deserialize)]
 struct VehicleQuery {
     vehicle_id: String,
 }
 
-#[get("/seats")]
-async fn get_seats(s: Session, q: web::Query<VehicleQuery>) -> impl Responder {
-    auth!(s);
+#[get("/seats")]
+async fn get_seats(s: Session, q: web::Query<VehicleQuery>) -> impl Responder {
+    verified!(s);
+
+    s.insert("vehicle_id", q.vehicle_id.clone()).unwrap();
+
+    let event_id: String = s.get("event_id").unwrap().unwrap();
+    let event_id = Uuid::parse_str(event_id.as_str()).unwrap();
+
+    let conn = db::connect();
+    let campuses = db::get_campuses(&conn).unwrap();
+
+    HttpResponse::Ok().body(
+        SeatsTemplate {
+            campuses,
+            error: signups_closed_error(&conn, event_id).unwrap_or_default(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+/// Seats form, read as pairs since every campus checkbox is named "campus"
+type SeatsData = Vec<(String, String)>;
+
+#[post("/seats")]
+async fn post_seats(s: Session, form: web::Form<SeatsData>, state: web::Data<AppState>) -> impl Responder {
+    verified!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let event_id: String = s.get("event_id").unwrap().unwrap();
+    let event_id = Uuid::parse_str(event_id.as_str()).unwrap();
+
+    let vehicle_id: String = s.get("vehicle_id").unwrap().unwrap();
+    let vehicle_id = Uuid::parse_str(vehicle_id.as_str()).unwrap();
+
+    let value = |name: &str| {
+        form.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or("")
+    };
+
+    let conn = db::connect();
+    let all_campuses = db::get_campuses(&conn).unwrap();
+
+    if let Some(error) = signups_closed_error(&conn, event_id) {
+        return HttpResponse::Ok().body(
+            SeatsTemplate {
+                campuses: all_campuses,
+                error,
+            }
+            .render()
+            .unwrap(),
+        );
+    }
+
+    let campuses: Vec<CampusCode> = form.iter()
+        .filter(|(key, _)| key == "campus")
+        .filter_map(|(_, code)| code.parse().ok())
+        .filter(|code| all_campuses.iter().any(|campus| &campus.code == code))
+        .collect();
+
+    let seats = value("seats").trim().parse::<usize>().ok();
+    let legs = parse_legs(value("legs"));
+
+    let error = if campuses.is_empty() {
+        Some("Select at least one campus")
+    } else if seats.is_none() {
+        Some("Available seats must be a number")
+    } else if legs.is_none() {
+        Some("Select which way you are driving")
+    } else {
+        None
+    };
+
+    if let Some(error) = error {
+        return HttpResponse::Ok().body(
+            SeatsTemplate {
+                campuses: all_campuses,
+                error: error.into(),
+            }
+            .render()
+            .unwrap(),
+        );
+    }
+
+    let seats = seats.unwrap();
+    for leg in legs.unwrap() {
+        db::create_driver(&conn, id, event_id, vehicle_id, seats, &campuses, leg).unwrap();
+    }
+
+    // Notify worker thread
+    state.tx.send(()).unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/"))
+        .finish()
+}
+
+#[derive(Debug, Deserialize)]
+struct SignupQuery {
+    invite_id: Option<String>,
+}
+
+/// Get an invite that can still be signed up with
+fn usable_invite(conn: &sqlite::Connection, invite_id: &str) -> Result<Invite, &'static str> {
+    let invite = Uuid::parse_str(invite_id).ok()
+        .and_then(|id| db::get_invite(conn, id).unwrap())
+        .ok_or("This invite link isn't valid")?;
+
+    invite.check(Local::now().naive_local())?;
+    Ok(invite)
+}
+
+/// Render the sign up form for an invite
+fn render_signup(invite: &Invite, error: &str) -> HttpResponse {
+    HttpResponse::Ok().body(
+        SignupTemplate {
+            invite_id: invite.id,
+            email: invite.email.clone().unwrap_or_default(),
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+#[get("/signup")]
+async fn get_signup(q: web::Query<SignupQuery>) -> impl Responder {
+    let conn = db::connect();
+
+    match q.invite_id.as_deref().map(|id| usable_invite(&conn, id)) {
+        Some(Ok(invite)) => render_signup(&invite, ""),
+        Some(Err(error)) => HttpResponse::Ok().body(
+            LoginTemplate {
+                error: error.into(),
+            }
+            .render()
+            .unwrap(),
+        ),
+        None => HttpResponse::SeeOther()
+            .append_header(("location", "/"))
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct SignupFormData {
+    invite_id: String,
+    name: String,
+    email: String,
+    password: String,
+    confirm_password: String,
+    phone: String,
+}
+
+#[post("/signup")]
+async fn post_signup(s: Session, form: web::Form<SignupFormData>) -> impl Responder {
+    let conn = db::connect();
+
+    let invite = match usable_invite(&conn, &form.invite_id) {
+        Ok(invite) => invite,
+        Err(error) => {
+            return HttpResponse::Ok().body(
+                LoginTemplate {
+                    error: error.into(),
+                }
+                .render()
+                .unwrap(),
+            )
+        }
+    };
+
+    let email = form.email.trim().to_string();
+
+    if !invite.allows_email(&email) {
+        return render_signup(&invite, "This invite is for a different email");
+    }
+
+    if db::get_user_by_email(&conn, email.clone()).unwrap().is_some() {
+        return render_signup(&invite, "An account already exists for this email");
+    }
+
+    if form.password != form.confirm_password {
+        return render_signup(&invite, "Passwords do not match");
+    }
+
+    if form.password.len() < 8 {
+        return render_signup(&invite, "Password must be 8 characters or more");
+    }
+
+    let created = db::create_invited_user(
+        &conn,
+        invite.id,
+        email,
+        form.name.clone(),
+        form.password.clone(),
+        form.phone.clone(),
+    );
+
+    // Someone else signed up with the email since it was checked
+    if created.as_ref().is_err_and(|error| db::is_constraint_error(error.as_ref())) {
+        return render_signup(&invite, "An account already exists for this email");
+    }
+
+    let user = match created.unwrap() {
+        Some(user_id) => db::get_user(&conn, user_id).unwrap().unwrap(),
+        // Other sign ups used the invite up since it was checked
+        None => return render_signup(&invite, "This invite can no longer be used"),
+    };
+
+    let token_id = db::create_verification(&conn, user.id).unwrap();
+    send_verification_email(&user.email, &token_id.to_string()).await.ok();
+
+    s.insert("logged_in", true).unwrap();
+    s.insert("user_id", user.id.to_string()).unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/"))
+        .finish()
+}
+
+#[get("/verify/{token}")]
+async fn get_verify_email(path: web::Path<(String,)>, state: web::Data<AppState>) -> impl Responder {
+    let conn = db::connect();
+
+    let verified = Uuid::parse_str(&path.0).ok()
+        .and_then(|id| db::verify_email(&conn, id).unwrap())
+        .and_then(|user_id| db::get_user(&conn, user_id).unwrap());
+
+    match verified {
+        Some(user) => {
+            promote_configured_admin(&conn, &user, state.admin_email.as_deref());
+            HttpResponse::SeeOther()
+                .append_header(("Location", "/"))
+                .finish()
+        }
+        None => HttpResponse::Ok().body(
+            LoginTemplate {
+                error: "That verification link isn't valid or has expired, log in to get a new one".into(),
+            }
+            .render()
+            .unwrap(),
+        )
+    }
+}
+
+#[post("/verify/resend")]
+async fn resend_verification(s: Session) -> impl Responder {
+    auth!(s);
+
+    let conn = db::connect();
+    let user = s.get::<String>("user_id").unwrap()
+        .and_then(|id| Uuid::parse_str(&id).ok())
+        .and_then(|id| db::get_user(&conn, id).unwrap());
+
+    if let Some(user) = user.filter(|user| !user.verified) {
+        let token_id = db::create_verification(&conn, user.id).unwrap();
+        send_verification_email(&user.email, &token_id.to_string()).await.ok();
+    }
+
+    HttpResponse::Ok().body(
+        CheckEmailTemplate {
+            heading: "Verify Your Email",
+            message: "Check your email for a new link to verify your account",
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+/// Render the invite manager
+fn render_invites(conn: &sqlite::Connection, error: &str) -> HttpResponse {
+    let invites = db::get_invites(conn).unwrap()
+        .into_iter()
+        .map(|invite| {
+            let name = invite.created_by
+                .and_then(|id| db::get_user(conn, id).unwrap())
+                .map(|user| user.fullname)
+                .unwrap_or_else(|| "INVITE_ID".into());
+            (invite, name)
+        })
+        .collect();
+
+    HttpResponse::Ok().body(
+        InvitesTemplate {
+            invites,
+            now: Local::now().naive_local(),
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+#[get("/invites")]
+async fn get_invites(s: Session) -> impl Responder {
+    role!(s, Role::Admin);
+
+    let conn = db::connect();
+    render_invites(&conn, "")
+}
+
+#[derive(Deserialize)]
+struct InviteForm {
+    email: Option<String>,
+    /// From a datetime-local input
+    expires_at: Option<String>,
+    max_uses: Option<String>,
+}
+
+#[post("/invites")]
+async fn post_invites(s: Session, form: web::Form<InviteForm>) -> impl Responder {
+    role!(s, Role::Admin);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+
+    let email = form.email.as_deref().map(str::trim).filter(|email| !email.is_empty());
+
+    let expires_at = match form.expires_at.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(value) => match NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+            Ok(time) => Some(time),
+            Err(_) => return render_invites(&conn, "Enter a valid expiry time"),
+        }
+    };
+
+    let max_uses = match form.max_uses.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(value) => match value.parse::<i64>() {
+            Ok(uses) if uses > 0 => Some(uses),
+            _ => return render_invites(&conn, "Max uses must be a positive number"),
+        }
+    };
+
+    db::create_invite(&conn, Uuid::new_v4(), Some(id), email.map(String::from), expires_at, max_uses).unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/invites"))
+        .finish()
+}
+
+#[post("/invites/{id}/revoke")]
+async fn revoke_invite(s: Session, path: web::Path<(String,)>) -> impl Responder {
+    role!(s, Role::Admin);
+
+    let conn = db::connect();
+    if let Ok(invite_id) = Uuid::parse_str(&path.0) {
+        db::revoke_invite(&conn, invite_id).unwrap();
+    }
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/invites"))
+        .finish()
+}
+
+/// Make the user set by the ADMIN_EMAIL environment variable an admin,
+/// so there is always someone who can hand out roles.
+/// Only once they have verified the address, so signing up with it isn't enough
+fn promote_configured_admin(conn: &sqlite::Connection, user: &User, admin_email: Option<&str>) {
+    if user.verified && admin_email.is_some_and(|email| email.eq_ignore_ascii_case(user.email.trim())) && user.role != Role::Admin {
+        info!("Promoting {} to admin", user.email);
+        db::set_role(conn, user.id, Role::Admin).unwrap();
+    }
+}
+
+#[get("/devices")]
+async fn get_devices(s: Session) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let current = s.get::<String>("device_id").unwrap()
+        .and_then(|device_id| Uuid::parse_str(&device_id).ok());
+
+    let conn = db::connect();
+    HttpResponse::Ok().body(
+        DevicesTemplate {
+            devices: db::get_user_devices(&conn, id).unwrap(),
+            current,
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+/// Forget a device. Revoking the current device logs it out on the next page
+#[post("/devices/{id}/revoke")]
+async fn revoke_device(s: Session, path: web::Path<(String,)>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    if let Ok(device_id) = Uuid::parse_str(&path.0) {
+        db::delete_device(&conn, id, device_id).unwrap();
+    }
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/devices"))
+        .finish()
+}
+
+#[get("/users")]
+async fn get_users(s: Session) -> impl Responder {
+    role!(s, Role::Admin);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    HttpResponse::Ok().body(
+        UsersTemplate {
+            users: db::get_users(&conn).unwrap(),
+            user_id: id,
+            roles: Role::ALL,
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+#[derive(Deserialize)]
+struct UserRoleForm {
+    user_id: String,
+    role: String,
+}
+
+#[post("/users")]
+async fn post_users(s: Session, form: web::Form<UserRoleForm>) -> impl Responder {
+    role!(s, Role::Admin);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    // Only roles admins can hand out, guests are made by the guest ride form
+    let role = Role::ALL.into_iter().find(|role| role.code() == form.role);
+
+    // Admins can't change their own role, so there is always at least one admin
+    let conn = db::connect();
+    let user = Uuid::parse_str(&form.user_id).ok()
+        .filter(|user_id| *user_id != id)
+        .and_then(|user_id| db::get_user(&conn, user_id).unwrap())
+        .filter(|user| user.role != Role::Guest);
+
+    if let (Some(user), Some(role)) = (user, role) {
+        db::set_role(&conn, user.id, role).unwrap();
+    }
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/users"))
+        .finish()
+}
+
+/// Render the event manager with the events the user can edit
+fn render_manage_events(conn: &sqlite::Connection, user_id: Uuid, role: Role, error: &str) -> HttpResponse {
+    let events = db::get_events(conn).unwrap()
+        .into_iter()
+        .filter(|event| can_manage_event(event, user_id, role))
+        .collect();
+
+    let cancelled = db::get_cancelled_events(conn).unwrap()
+        .into_iter()
+        .filter(|event| event.creator_id == user_id || role == Role::Admin)
+        .map(|event| {
+            let name = event.cancelled_by
+                .and_then(|id| db::get_user(conn, id).unwrap())
+                .map(|user| user.fullname)
+                .unwrap_or_default();
+            (event, name)
+        })
+        .collect();
+
+    HttpResponse::Ok().body(
+        ManageEventsTemplate {
+            events,
+            cancelled,
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+/// Whether a user can change an event, admins can change any event.
+/// Cancelled events can't be changed
+fn can_manage_event(event: &Event, user_id: Uuid, role: Role) -> bool {
+    !event.is_cancelled() && (event.creator_id == user_id || role == Role::Admin)
+}
+
+#[get("/manage_events")]
+async fn get_manage_events(s: Session) -> impl Responder {
+    role!(s, Role::Organizer);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    render_manage_events(&conn, id, session_role(&s), "")
+}
+
+#[derive(Deserialize)]
+struct ManageEventForm {
+    name: String,
+    date: String,
+    time: String,
+    address1: String,
+    address2: Option<String>,
+    city: String,
+    state: String,
+    zipcode: String,
+    strategy: Option<String>,
+    /// Optional cutoffs, from datetime-local inputs
+    signup_deadline: Option<String>,
+    freeze_time: Option<String>,
+}
+
+/// Times parsed from a ManageEventForm
+struct EventTimes {
+    time: NaiveDateTime,
+    signup_deadline: Option<NaiveDateTime>,
+    freeze_time: Option<NaiveDateTime>,
+}
+
+impl ManageEventForm {
+    /// Parse the event's times, checking cutoffs come before the event
+    fn times(&self) -> Result<EventTimes, &'static str> {
+        let time = NaiveDateTime::parse_from_str(&format!("{} {}", self.date, self.time), "%Y-%m-%d %H:%M")
+            .map_err(|_| "Enter a date and time for the event")?;
+
+        let cutoff = |value: &Option<String>| {
+            match value.as_deref().map(str::trim) {
+                None | Some("") => Ok(None),
+                Some(value) => NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M").map(Some)
+            }
+        };
+
+        let signup_deadline = cutoff(&self.signup_deadline)
+            .map_err(|_| "Enter a valid sign up deadline")?;
+        let freeze_time = cutoff(&self.freeze_time)
+            .map_err(|_| "Enter a valid time to lock in drivers")?;
+
+        if signup_deadline.is_some_and(|deadline| deadline > time) {
+            return Err("The sign up deadline must be before the event");
+        }
+
+        if freeze_time.is_some_and(|freeze| freeze > time) {
+            return Err("Drivers must be locked in before the event");
+        }
+
+        Ok(EventTimes {
+            time,
+            signup_deadline,
+            freeze_time,
+        })
+    }
+
+    /// Describe what riders and drivers need to know about an edit to an event,
+    /// which is only a new time or address
+    fn changes(&self, event: &Event, time: NaiveDateTime) -> Vec<String> {
+        let address2 = self.address2.clone().unwrap_or_default();
+
+        let mut changes = Vec::new();
+        if time != event.time {
+            changes.push(format!("New time: {}", time.format("%A, %B %d at %l:%M %p")));
+        }
+
+        let address_changed = self.address1 != event.address1
+            || address2 != event.address2
+            || self.city != event.city
+            || self.state != event.state
+            || self.zipcode != event.zipcode;
+        if address_changed {
+            changes.push(format!(
+                "New address: {} {}, {}, {} {}",
+                self.address1, address2, self.city, self.state, self.zipcode
+            ));
+        }
+
+        changes
+    }
+}
+
+#[post("/manage_events")]
+async fn post_manage_events(s: Session, form: web::Form<ManageEventForm>) -> impl Responder {
+    role!(s, Role::Organizer);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+
+    let times = match form.times() {
+        Ok(times) => times,
+        Err(error) => return render_manage_events(&conn, id, session_role(&s), error),
+    };
+
+    db::create_event(
+        &conn,
+        form.name.clone(),
+        times.time,
+        form.address1.clone(),
+        form.address2.clone().unwrap_or("".to_string()),
+        form.city.clone(),
+        form.state.clone(),
+        form.zipcode.clone(),
+        id,
+        form.strategy.clone().filter(|name| matching::get_strategy(name).is_some()),
+        times.signup_deadline,
+        times.freeze_time,
+    )
+    .unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/"))
+        .finish()
+}
+
+/// Render the edit form for an event
+fn render_edit_event(event: Event, error: &str) -> HttpResponse {
+    let format = |time: Option<NaiveDateTime>| {
+        time.map(|time| time.format("%Y-%m-%dT%H:%M").to_string())
+            .unwrap_or_default()
+    };
+
+    HttpResponse::Ok().body(
+        EditEventTemplate {
+            signup_deadline: format(event.signup_deadline),
+            freeze_time: format(event.freeze_time),
+            event,
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+#[get("/manage_events/{id}/edit")]
+async fn get_edit_event(s: Session, path: web::Path<(String,)>) -> impl Responder {
+    role!(s, Role::Organizer);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    let event = Uuid::parse_str(&path.0).ok()
+        .and_then(|event_id| db::get_event(&conn, event_id).unwrap());
+
+    match event {
+        Some(event) if can_manage_event(&event, id, session_role(&s)) => render_edit_event(event, ""),
+        _ => HttpResponse::SeeOther()
+            .append_header(("Location", "/manage_events"))
+            .finish()
+    }
+}
+
+#[post("/manage_events/{id}/edit")]
+async fn post_edit_event(s: Session, path: web::Path<(String,)>, form: web::Form<ManageEventForm>, state: web::Data<AppState>) -> impl Responder {
+    role!(s, Role::Organizer);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    let event = Uuid::parse_str(&path.0).ok()
+        .and_then(|event_id| db::get_event(&conn, event_id).unwrap());
+
+    let event = match event {
+        Some(event) if can_manage_event(&event, id, session_role(&s)) => event,
+        _ => {
+            return HttpResponse::SeeOther()
+                .append_header(("Location", "/manage_events"))
+                .finish()
+        }
+    };
+
+    let times = match form.times() {
+        Ok(times) => times,
+        Err(error) => return render_edit_event(event, error),
+    };
+
+    db::update_event(
+        &conn,
+        event.id,
+        form.name.clone(),
+        times.time,
+        form.address1.clone(),
+        form.address2.clone().unwrap_or_default(),
+        form.city.clone(),
+        form.state.clone(),
+        form.zipcode.clone(),
+        form.strategy.clone().filter(|name| matching::get_strategy(name).is_some()),
+        times.signup_deadline,
+        times.freeze_time,
+    )
+    .unwrap();
+
+    // Let riders and drivers know when and where they need to be
+    let changes = form.changes(&event, times.time);
+    if !changes.is_empty() {
+        for attendee in db::get_event_attendees(&conn, event.id).unwrap() {
+            let update = EventUpdate {
+                to: attendee.email,
+                event_name: form.name.clone(),
+                changes: changes.clone(),
+            };
+            state.updates.send(update).unwrap();
+        }
+    }
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/manage_events"))
+        .finish()
+}
+
+#[post("/manage_events/{id}/cancel")]
+async fn cancel_event(s: Session, path: web::Path<(String,)>) -> impl Responder {
+    role!(s, Role::Organizer);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    let event = Uuid::parse_str(&path.0).ok()
+        .and_then(|event_id| db::get_event(&conn, event_id).unwrap());
+
+    if let Some(event) = event.filter(|event| can_manage_event(event, id, session_role(&s))) {
+        // Attendees are gone once the event is cancelled
+        let attendees = db::get_event_attendees(&conn, event.id).unwrap();
+        db::cancel_event(&conn, event.id, id).unwrap();
+
+        let time = event.time.format("%A, %B %d at %l:%M %p").to_string();
+        for attendee in attendees {
+            send_event_cancelled(&attendee.email, &event.name, &time).await.ok();
+        }
+    }
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/manage_events"))
+        .finish()
+}
+
+#[get("/reset")]
+async fn get_reset_password() -> impl Responder {
+    ResetPasswordTemplate {}
+}
+
+#[derive(Deserialize)]
+struct RequestResetForm {
+    email: String
+}
+
+#[post("/reset")]
+async fn post_reset_password(form: web::Form<RequestResetForm>) -> impl Responder {
+    let conn = db::connect();
+
+    if let Ok(Some(user)) = db::get_user_by_email(&conn, form.email.clone()) {
+        let id = db::create_reset_request(&conn, user.id).unwrap();
+        send_reset_email(&user.email, &id.to_string()).await.ok();
+    }
+    
+    CheckEmailTemplate {
+        heading: "Reset Password",
+        message: "Check your email to reset your password, the link works once and expires soon",
+    }
+}
+
+/// Get a reset request that can still be used
+fn usable_reset_request(conn: &sqlite::Connection, id: &str) -> Result<ResetRequest, &'static str> {
+    let request = Uuid::parse_str(id).ok()
+        .and_then(|id| db::get_reset_request(conn, id).unwrap())
+        .ok_or("This reset link isn't valid. It may have already been used or replaced by a newer link.")?;
+
+    if request.is_expired(Local::now().naive_local()) {
+        db::delete_reset_request(conn, request.request_id).unwrap();
+        return Err("This reset link has expired.");
+    }
+
+    Ok(request)
+}
+
+/// Render the page explaining why a reset link can't be used
+fn render_reset_error(message: &'static str) -> HttpResponse {
+    HttpResponse::Ok().body(
+        ResetErrorTemplate { message }
+            .render()
+            .unwrap(),
+    )
+}
+
+/// Render the form to choose a new password
+fn render_set_password(error: &str) -> HttpResponse {
+    HttpResponse::Ok().body(
+        SetPasswordTemplate {
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+#[get("/reset/{id}")]
+async fn get_reset_password_with_id(path: web::Path<(String,)>) -> impl Responder {
+    let conn = db::connect();
+
+    match usable_reset_request(&conn, &path.0) {
+        Ok(_) => render_set_password(""),
+        Err(error) => render_reset_error(error),
+    }
+}
+
+#[derive(Deserialize)]
+struct ResetForm {
+    password: String,
+    confirm_password: String,
+}
+
+#[post("/reset/{id}")]
+async fn post_reset_password_with_id(s: Session, req: HttpRequest, path: web::Path<(String,)>, form: web::Form<ResetForm>) -> impl Responder {
+    let conn = db::connect();
+
+    let request = match usable_reset_request(&conn, &path.0) {
+        Ok(request) => request,
+        Err(error) => return render_reset_error(error),
+    };
+
+    if form.password != form.confirm_password {
+        return render_set_password("Passwords do not match");
+    }
+
+    if form.password.len() < 8 {
+        return render_set_password("Password must be 8 characters or more");
+    }
+
+    // Another request could have used the link since it was checked
+    let user = db::redeem_reset_request(&conn, request.request_id).unwrap()
+        .and_then(|request| db::get_user(&conn, request.user_id).unwrap());
+
+    match user {
+        Some(mut user) => {
+            db::set_password(&conn, user.id, form.password.clone()).unwrap();
+            // The link was sent to their email, so they own it
+            db::set_verified(&conn, user.id).unwrap();
+            user.verified = true;
+            log_in(&s, &req, &conn, &user, false)
+        }
+        None => render_reset_error("This reset link was already used."),
+    }
+}
+
+/// Find new seats right away for riders whose driver dropped out or gave up seats.
+/// Leadership is alerted about anyone left without one
+async fn reassign_cancelled(conn: &sqlite::Connection, event_id: Uuid) {
+    let strategy = matching::configured_strategy();
+    let priority = matching::configured_priority();
+    let stranded = db::reassign_cancelled_rides(conn, event_id, strategy.as_ref(), priority).unwrap();
+
+    if !stranded.is_empty() {
+        let event = db::get_event(conn, event_id).unwrap().unwrap();
+        let riders: Vec<String> = stranded.iter()
+            .filter_map(|ride| db::get_user(conn, ride.rider_id).unwrap())
+            .map(|user| format!("{} ({})", user.fullname, user.number))
+            .collect();
+
+        send_leadership_alert(&event.name, &riders).await.ok();
+    }
+}
+
+/// Render the form to change a ride
+fn render_edit_ride(conn: &sqlite::Connection, event: Event, ride: Ride, error: &str) -> HttpResponse {
+    HttpResponse::Ok().body(
+        EditRideTemplate {
+            event,
+            campuses: db::get_campuses(conn).unwrap(),
+            ride,
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+/// Get an event and the user's ride to it, from either leg
+fn event_ride(conn: &sqlite::Connection, event_id: &str, user_id: Uuid) -> Option<(Event, Ride)> {
+    let event = Uuid::parse_str(event_id).ok()
+        .and_then(|event_id| db::get_event(conn, event_id).unwrap())?;
+
+    let ride = [Leg::To, Leg::Return].into_iter()
+        .find_map(|leg| db::get_ride(conn, event.id, user_id, leg).unwrap())?;
+
+    Some((event, ride))
+}
+
+#[get("/rides/{event_id}/edit")]
+async fn get_edit_ride(s: Session, path: web::Path<(String,)>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    match event_ride(&conn, &path.0, id) {
+        Some((event, ride)) => {
+            let error = signups_closed_error(&conn, event.id).unwrap_or_default();
+            render_edit_ride(&conn, event, ride, &error)
+        }
+        None => HttpResponse::SeeOther()
+            .append_header(("Location", "/"))
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct EditRideForm {
+    campus: String,
+    pickup: String,
+}
+
+#[post("/rides/{event_id}/edit")]
+async fn post_edit_ride(s: Session, path: web::Path<(String,)>, form: web::Form<EditRideForm>, state: web::Data<AppState>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    let (event, ride) = match event_ride(&conn, &path.0, id) {
+        Some(event_ride) => event_ride,
+        None => {
+            return HttpResponse::SeeOther()
+                .append_header(("Location", "/"))
+                .finish()
+        }
+    };
+
+    if let Some(error) = signups_closed_error(&conn, event.id) {
+        return render_edit_ride(&conn, event, ride, &error);
+    }
+
+    let campus = form.campus.parse::<CampusCode>().ok()
+        .and_then(|code| db::get_campus(&conn, &code).unwrap());
+    let campus = match campus {
+        Some(campus) => campus,
+        None => return render_edit_ride(&conn, event, ride, "Select a campus"),
+    };
+
+    // Changing campus unassigns the rider's group, which would undo leadership's placements
+    if campus.code != ride.campus {
+        let groups: Vec<Uuid> = [Leg::To, Leg::Return].into_iter()
+            .filter_map(|leg| db::get_ride(&conn, event.id, id, leg).unwrap())
+            .filter_map(|ride| ride.group_id)
+            .collect();
+
+        let pinned = db::get_event_rides(&conn, event.id).unwrap()
+            .iter()
+            .filter(|ride| ride.rider_id == id || ride.group_id.is_some_and(|group_id| groups.contains(&group_id)))
+            .any(|ride| ride.pinned);
+
+        if pinned {
+            return render_edit_ride(&conn, event, ride, "Leadership has placed you or your group in a car, reach out to them to change campus");
+        }
+    }
+
+    db::update_ride(&conn, id, event.id, &campus, form.pickup.clone()).unwrap();
+
+    // Notify worker thread
+    state.tx.send(()).unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/"))
+        .finish()
+}
+
+/// Render the form to change a drive
+fn render_edit_drive(conn: &sqlite::Connection, event: Event, drivers: Vec<Driver>, error: &str) -> HttpResponse {
+    HttpResponse::Ok().body(
+        EditDriveTemplate {
+            event,
+            vehicles: db::get_driver_vehicles(conn, drivers[0].driver_id).unwrap(),
+            vehicle_id: drivers[0].vehicle_id,
+            drivers: drivers.into_iter()
+                .map(|driver| {
+                    let leg: &'static str = driver.leg.into();
+                    (driver, leg)
+                })
+                .collect(),
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+/// Get an event and the user's drive to it for every leg they drive
+fn event_drive(conn: &sqlite::Connection, event_id: &str, user_id: Uuid) -> Option<(Event, Vec<Driver>)> {
+    let event = Uuid::parse_str(event_id).ok()
+        .and_then(|event_id| db::get_event(conn, event_id).unwrap())?;
+
+    let drivers = db::get_user_drivers(conn, event.id, user_id).unwrap();
+    if drivers.is_empty() {
+        return None;
+    }
+
+    Some((event, drivers))
+}
+
+#[get("/drives/{event_id}/edit")]
+async fn get_edit_drive(s: Session, path: web::Path<(String,)>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    match event_drive(&conn, &path.0, id) {
+        Some((event, drivers)) => {
+            let error = signups_closed_error(&conn, event.id).unwrap_or_default();
+            render_edit_drive(&conn, event, drivers, &error)
+        }
+        None => HttpResponse::SeeOther()
+            .append_header(("Location", "/"))
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct EditDriveForm {
+    vehicle_id: String,
+    /// Seats for the leg to the event, if the user drives it
+    #[serde(rename = "seats_TO")]
+    seats_to: Option<String>,
+    /// Seats for the return leg, if the user drives it
+    #[serde(rename = "seats_RETURN")]
+    seats_return: Option<String>,
+}
+
+#[post("/drives/{event_id}/edit")]
+async fn post_edit_drive(s: Session, path: web::Path<(String,)>, form: web::Form<EditDriveForm>, state: web::Data<AppState>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let conn = db::connect();
+    let (event, drivers) = match event_drive(&conn, &path.0, id) {
+        Some(event_drive) => event_drive,
+        None => {
+            return HttpResponse::SeeOther()
+                .append_header(("Location", "/"))
+                .finish()
+        }
+    };
+
+    if let Some(error) = signups_closed_error(&conn, event.id) {
+        return render_edit_drive(&conn, event, drivers, &error);
+    }
+
+    let vehicle = match owned_vehicle(&conn, &form.vehicle_id, id) {
+        Some(vehicle) => vehicle,
+        None => return render_edit_drive(&conn, event, drivers, "Select a vehicle"),
+    };
+
+    let seats: Option<Vec<(Leg, usize)>> = drivers.iter()
+        .map(|driver| {
+            let seats = match driver.leg {
+                Leg::To => form.seats_to.as_deref(),
+                Leg::Return => form.seats_return.as_deref(),
+            };
+            seats.and_then(|seats| seats.trim().parse::<usize>().ok())
+                .map(|seats| (driver.leg, seats))
+        })
+        .collect();
+
+    let seats = match seats {
+        Some(seats) => seats,
+        None => return render_edit_drive(&conn, event, drivers, "Available seats must be a number"),
+    };
+
+    let mut bumped = false;
+    for (leg, seats) in seats {
+        bumped |= db::update_driver(&conn, id, event.id, vehicle.id, seats, leg).unwrap();
+    }
+
+    // Riders who no longer fit need a new car right away
+    if bumped {
+        reassign_cancelled(&conn, event.id).await;
+    }
+
+    // Notify worker thread
+    state.tx.send(()).unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/"))
+        .finish()
+}
+
+/// Render the ride request form for someone without an account
+fn render_guest_ride(conn: &sqlite::Connection, event: Event, error: &str) -> HttpResponse {
+    HttpResponse::Ok().body(
+        GuestRideTemplate {
+            event,
+            campuses: db::get_campuses(conn).unwrap(),
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+/// Get an event from a shared link, if it can still be signed up for
+fn guest_event(conn: &sqlite::Connection, event_id: &str) -> Option<Event> {
+    Uuid::parse_str(event_id).ok()
+        .and_then(|event_id| db::get_event(conn, event_id).unwrap())
+        .filter(|event| !event.is_cancelled())
+}
+
+/// Shareable link for an event, so occasional riders don't need an account
+#[get("/events/{id}/guest")]
+async fn get_guest_ride(path: web::Path<(String,)>) -> impl Responder {
+    let conn = db::connect();
+
+    match guest_event(&conn, &path.0) {
+        Some(event) => {
+            let error = signups_closed_error(&conn, event.id).unwrap_or_default();
+            render_guest_ride(&conn, event, &error)
+        }
+        None => HttpResponse::SeeOther()
+            .append_header(("Location", "/login"))
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct GuestRideForm {
+    name: String,
+    phone: String,
+    campus: String,
+    pickup: String,
+    legs: String,
+}
+
+#[post("/events/{id}/guest")]
+async fn post_guest_ride(path: web::Path<(String,)>, form: web::Form<GuestRideForm>, state: web::Data<AppState>) -> impl Responder {
+    let conn = db::connect();
+
+    let event = match guest_event(&conn, &path.0) {
+        Some(event) => event,
+        None => {
+            return HttpResponse::SeeOther()
+                .append_header(("Location", "/login"))
+                .finish()
+        }
+    };
+
+    if let Some(error) = signups_closed_error(&conn, event.id) {
+        return render_guest_ride(&conn, event, &error);
+    }
+
+    if form.name.trim().is_empty() || form.phone.trim().is_empty() {
+        return render_guest_ride(&conn, event, "Enter your name and phone number");
+    }
+
+    let campus = form.campus.parse::<CampusCode>().ok()
+        .and_then(|code| db::get_campus(&conn, &code).unwrap());
+    let campus = match campus {
+        Some(campus) => campus,
+        None => return render_guest_ride(&conn, event, "Select a campus"),
+    };
+
+    let legs = match parse_legs(&form.legs) {
+        Some(legs) => legs,
+        None => return render_guest_ride(&conn, event, "Select which way you need a ride"),
+    };
+
+    let (guest_id, token) = db::create_guest(&conn, form.name.trim().into(), form.phone.trim().into()).unwrap();
+    for leg in legs {
+        db::create_ride(&conn, guest_id, event.id, &campus, form.pickup.clone(), None, leg).unwrap();
+    }
+
+    // Notify worker thread
+    state.tx.send(()).unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", format!("/guest/{}", token)))
+        .finish()
+}
+
+/// Private status page for a guest's ride, only reachable with their token
+#[get("/guest/{token}")]
+async fn get_guest_status(path: web::Path<(String,)>) -> impl Responder {
+    let conn = db::connect();
+    let guest = Uuid::parse_str(&path.0).ok()
+        .and_then(|token| db::get_guest(&conn, token).unwrap());
+
+    match guest {
+        Some(guest) => {
+            let priority = matching::configured_priority();
+            let events_data = db::get_events_data(&conn, guest.id, priority).unwrap();
+
+            HttpResponse::Ok().body(
+                GuestStatusTemplate {
+                    guest,
+                    events_data,
+                }
+                .render()
+                .unwrap(),
+            )
+        }
+        None => HttpResponse::SeeOther()
+            .append_header(("Location", "/login"))
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteQuery {
+    event_id: String,
+}
+
+#[post("/events/delete")]
+async fn delete_event(s: Session, q: web::Query<DeleteQuery>, state: web::Data<AppState>) -> impl Responder {
+    auth!(s);
+
+    let id: String = s.get("user_id").unwrap().unwrap();
+    let id = Uuid::parse_str(id.as_str()).unwrap();
+
+    let event_id = Uuid::parse_str(q.event_id.as_str()).unwrap();
+
+    let conn = db::connect();
+    let was_driving = db::get_driver_events(&conn, id).unwrap()
+        .iter()
+        .any(|event| event.id == event_id);
+
+    db::delete_user_event(&conn, id, event_id).unwrap();
+
+    // Find new seats for the driver's passengers right away
+    if was_driving {
+        reassign_cancelled(&conn, event_id).await;
+    }
+
+    // Notify worker thread
+    state.tx.send(()).unwrap();
+
+    HttpResponse::SeeOther()
+        .append_header(("Location", "/"))
+        .finish()
+}
+
+#[get("/events/info")]
+async fn events_info(s: Session) -> impl Responder {
+    role!(s, Role::Organizer);
+
+    let conn = db::connect();
+    let events = db::get_events_info(&conn).unwrap();
+
+    HttpResponse::Ok().body(
+        EventsInfoTemplate {
+            events
+        }
+        .render()
+        .unwrap()
+    )
+}
+
+/// Encode assignments so a preview can be checked against a later run
+fn encode_plan(assignments: &[Assignment]) -> String {
+    let mut plan: Vec<String> = assignments.iter()
+        .map(|a| {
+            let leg: &str = a.leg.into();
+            format!("{}:{}:{}:{}", a.event_id, a.rider_id, a.driver_id, leg)
+        })
+        .collect();
+    plan.sort();
+    plan.join(";")
+}
+
+/// Render the assignments the matcher would make right now
+fn render_preview(conn: &sqlite::Connection, message: &str) -> HttpResponse {
+    let strategy = matching::configured_strategy();
+    let priority = matching::configured_priority();
+    let assignments = db::preview_matches(conn, strategy.as_ref(), priority).unwrap();
+
+    let name = |id: Uuid| {
+        db::get_user(conn, id).unwrap()
+            .map(|user| user.fullname)
+            .unwrap_or_default()
+    };
+
+    let rows = assignments.iter()
+        .map(|a| PreviewRow {
+            event: db::get_event(conn, a.event_id).unwrap()
+                .map(|event| event.name)
+                .unwrap_or_default(),
+            leg: a.leg.label(),
+            rider: name(a.rider_id),
+            driver: name(a.driver_id),
+        })
+        .collect();
+
+    HttpResponse::Ok().body(
+        MatchPreviewTemplate {
+            rows,
+            plan: encode_plan(&assignments),
+            message: message.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
+
+#[get("/matching")]
+async fn get_matching(s: Session) -> impl Responder {
+    role!(s, Role::Organizer);
+
+    let conn = db::connect();
+    render_preview(&conn, "")
+}
+
+#[derive(Deserialize)]
+struct ApplyMatchingForm {
+    plan: String,
+}
+
+#[post("/matching/apply")]
+async fn apply_matching(s: Session, form: web::Form<ApplyMatchingForm>) -> impl Responder {
+    role!(s, Role::Organizer);
+
+    let conn = db::connect();
+    let strategy = matching::configured_strategy();
+    let priority = matching::configured_priority();
+
+    // Only apply what leadership saw, sign ups may have changed since
+    let current = db::preview_matches(&conn, strategy.as_ref(), priority).unwrap();
+    if encode_plan(&current) != form.plan {
+        return render_preview(&conn, "Sign ups changed since the preview, check the new matches before applying");
+    }
+
+    db::match_rides(&conn, strategy.as_ref(), priority).unwrap();
+
+    render_preview(&conn, "Matches applied")
+}
+
+/// Render every ride and driver for an event so leadership can adjust them
+fn render_assignments(conn: &sqlite::Connection, event: Event, error: &str) -> HttpResponse {
+    let name = |id: Uuid| {
+        db::get_user(conn, id).unwrap()
+            .map(|user| user.fullname)
+            .unwrap_or_default()
+    };
 
-    s.insert("vehicle_id", q.vehicle_id.clone()).unwrap();
+    let rides = db::get_event_rides(conn, event.id).unwrap()
+        .into_iter()
+        .map(|ride| RideRow {
+            rider: name(ride.rider_id),
+            leg: ride.leg.into(),
+            driver: ride.driver_id.map(name),
+            ride,
+        })
+        .collect();
+
+    let drivers = db::get_event_drivers(conn, event.id).unwrap()
+        .into_iter()
+        .map(|(driver, taken)| DriverRow {
+            name: name(driver.driver_id),
+            driver,
+            taken,
+        })
+        .collect();
+
+    let changes = db::get_assignment_changes(conn, event.id).unwrap()
+        .into_iter()
+        .map(|change| ChangeRow {
+            time: change.change_time,
+            rider: name(change.rider_id),
+            leg: change.leg.label(),
+            driver: change.driver_id.map(name),
+            pinned: change.pinned,
+            changed_by: name(change.changed_by),
+        })
+        .collect();
+
+    HttpResponse::Ok().body(
+        AssignmentsTemplate {
+            event,
+            rides,
+            drivers,
+            changes,
+            error: error.into(),
+        }
+        .render()
+        .unwrap(),
+    )
+}
 
-    HttpResponse::Ok().body(SeatsTemplate {}.render().unwrap())
+#[get("/assignments")]
+async fn get_assignments(s: Session, q: web::Query<EventQuery>) -> impl Responder {
+    role!(s, Role::Organizer);
+
+    let conn = db::connect();
+    let event = Uuid::parse_str(&q.event_id).ok()
+        .and_then(|id| db::get_event(&conn, id).unwrap());
+
+    match event {
+        Some(event) => render_assignments(&conn, event, ""),
+        None => HttpResponse::SeeOther()
+            .append_header(("Location", "/events?flow=assign"))
+            .finish()
+    }
 }
 
 #[derive(Deserialize)]
-struct SeatsData {
-    campus: String,
-    seats: usize,
+struct AssignmentForm {
+    event_id: String,
+    rider_id: String,
+    leg: String,
+    driver_id: Option<String>,
+    action: String,
 }
 
-#[post("/seats")]
-async fn post_seats(s: Session, form: web::Form<SeatsData>, state: web::Data<AppState>) -> impl Responder {
-    auth!(s);
+#[post("/assignments")]
+async fn post_assignment(s: Session, form: web::Form<AssignmentForm>, state: web::Data<AppState>) -> impl Responder {
+    role!(s, Role::Organizer);
 
     let id: String = s.get("user_id").unwrap().unwrap();
     let id = Uuid::parse_str(id.as_str()).unwrap();
 
-    let event_id: String = s.get("event_id").unwrap().unwrap();
-    let event_id = Uuid::parse_str(event_id.as_str()).unwrap();
+    let conn = db::connect();
 
-    let vehicle_id: String = s.get("vehicle_id").unwrap().unwrap();
-    let vehicle_id = Uuid::parse_str(vehicle_id.as_str()).unwrap();
+    let event = Uuid::parse_str(&form.event_id).ok()
+        .and_then(|id| db::get_event(&conn, id).unwrap());
+    let event = match event {
+        Some(event) => event,
+        None => {
+            return HttpResponse::SeeOther()
+                .append_header(("Location", "/events?flow=assign"))
+                .finish()
+        }
+    };
 
-    let campus: Campus = form.campus.as_str().into();
+    let leg: Leg = form.leg.as_str().into();
+    let ride = Uuid::parse_str(&form.rider_id).ok()
+        .and_then(|rider_id| db::get_ride(&conn, event.id, rider_id, leg).unwrap());
+    let ride = match ride {
+        Some(ride) => ride,
+        None => return render_assignments(&conn, event, "That ride no longer exists"),
+    };
 
-    let conn = db::connect();
-    db::create_driver(&conn, id, event_id, vehicle_id, form.seats, campus).unwrap();
+    // Manual moves are pinned so the matcher leaves them alone
+    let change = match form.action.as_str() {
+        "Move" => {
+            let driver_id = form.driver_id.as_deref().and_then(|id| Uuid::parse_str(id).ok());
+            let driver = db::get_event_drivers(&conn, event.id).unwrap()
+                .into_iter()
+                .find(|(driver, _)| Some(driver.driver_id) == driver_id && driver.leg == leg);
+
+            match driver {
+                None => Err("Select a driver for this leg"),
+                Some((driver, taken)) if taken >= driver.seats && ride.driver_id != driver_id => {
+                    Err("That car is full")
+                }
+                Some((driver, _)) => Ok((Some(driver.driver_id), true)),
+            }
+        }
+        "Unassign" => Ok((None, true)),
+        "Pin" => Ok((ride.driver_id, true)),
+        "Unpin" => Ok((ride.driver_id, false)),
+        _ => Err("Unknown action"),
+    };
 
-    // Notify worker thread
+    let (driver_id, pinned) = match change {
+        Ok(change) => change,
+        Err(error) => return render_assignments(&conn, event, error),
+    };
+
+    db::set_assignment(&conn, event.id, ride.rider_id, leg, driver_id, pinned, id).unwrap();
+
+    // Unpinned riders without a driver can be matched again
     state.tx.send(()).unwrap();
 
     HttpResponse::SeeOther()
-        .append_header(("Location", "/"))
+        .append_header(("Location", format!("/assignments?event_id={}", event.id)))
         .finish()
 }
 
-#[derive(Debug, Deserialize)]
-struct SignupQuery {
-    invite_id: Option<String>,
-}
-
-#[get("/signup")]
-async fn get_signup(q: web::Query<SignupQuery>) -> impl Responder {
-    if q.invite_id.is_none() {
-        return HttpResponse::SeeOther()
-            .append_header(("location", "/"))
-            .finish();
-    }
+#[get("/campuses")]
+async fn get_campuses(s: Session) -> impl Responder {
+    role!(s, Role::Admin);
 
-    let invite_id = Uuid::parse_str(q.invite_id.as_ref().unwrap()).unwrap();
-    if invite_id != *INVITE_ID {
-        return HttpResponse::SeeOther()
-            .append_header(("location", "/"))
-            .finish();
-    }
+    let conn = db::connect();
+    let campuses = db::get_campuses(&conn).unwrap();
 
-    HttpResponse::Ok().body(SignupTemplate { error: "".into() }.render().unwrap())
+    HttpResponse::Ok().body(
+        CampusesTemplate {
+            campuses,
+            error: "".into(),
+        }
+        .render()
+        .unwrap(),
+    )
 }
 
 #[derive(Deserialize)]
-struct SignupFormData {
+struct CampusForm {
+    code: String,
     name: String,
-    email: String,
-    password: String,
-    confirm_password: String,
-    phone: String,
 }
 
-#[post("/signup")]
-async fn post_signup(s: Session, form: web::Form<SignupFormData>) -> impl Responder {
-    if form.password != form.confirm_password {
-        return HttpResponse::Ok().body(
-            SignupTemplate {
-                error: "Passwords do not match".into(),
-            }
-            .render()
-            .unwrap(),
-        );
-    }
+#[post("/campuses")]
+async fn post_campus(s: Session, form: web::Form<CampusForm>) -> impl Responder {
+    role!(s, Role::Admin);
 
-    if form.password.len() < 8 {
+    let conn = db::connect();
+
+    let error = match form.code.parse::<CampusCode>() {
+        Err(_) => Some("Campus codes can only use letters and numbers".to_string()),
+        Ok(code) if db::get_campus(&conn, &code).unwrap().is_some() => {
+            Some(format!("A campus with code {code} already exists"))
+        }
+        Ok(code) => {
+            db::create_campus(&conn, &code, form.name.trim().to_string()).unwrap();
+            None
+        }
+    };
+
+    if let Some(error) = error {
         return HttpResponse::Ok().body(
-            SignupTemplate {
-                error: "Password must be 8 characters or more".into(),
+            CampusesTemplate {
+                campuses: db::get_campuses(&conn).unwrap(),
+                error,
             }
             .render()
             .unwrap(),
         );
     }
 
-    let conn = db::connect();
-    db::create_user(
-        &conn,
-        form.email.clone(),
-        form.name.clone(),
-        form.password.clone(),
-        form.phone.clone(),
-    )
-    .unwrap();
-    let user = db::get_user_by_email(&conn, form.email.clone())
-        .unwrap()
-        .unwrap();
-
-    s.insert("logged_in", true).unwrap();
-    s.insert("user_id", user.id.to_string()).unwrap();
-
     HttpResponse::SeeOther()
-        .append_header(("Location", "/"))
+        .append_header(("Location", "/campuses"))
         .finish()
 }
 
-#[get("/manage_events")]
-async fn get_manage_events(s: Session) -> impl Responder {
-    auth!(s);
-
-    HttpResponse::Ok().body(ManageEventsTemplate {}.render().unwrap())
-}
-
-#[derive(Deserialize)]
-struct ManageEventForm {
-    name: String,
-    date: String,
-    time: String,
-    address1: String,
-    address2: Option<String>,
-    city: String,
-    state: String,
-    zipcode: String,
-}
-
-#[post("/manage_events")]
-async fn post_manage_events(s: Session, form: web::Form<ManageEventForm>) -> impl Responder {
-    auth!(s);
+#[post("/campuses/{code}/delete")]
+async fn delete_campus(s: Session, path: web::Path<(String,)>) -> impl Responder {
+    role!(s, Role::Admin);
 
-    let time =
-        NaiveDateTime::parse_from_str(&format!("{} {}", form.date, form.time), "%Y-%m-%d %H:%M")
-            .unwrap();
+    if let Ok(code) = path.0.parse::<CampusCode>() {
+        let conn = db::connect();
 
-    let id: String = s.get("user_id").unwrap().unwrap();
-    let id = Uuid::parse_str(id.as_str()).unwrap();
+        // Rides left on a missing campus would never be matched
+        if db::count_campus_signups(&conn, &code).unwrap() > 0 {
+            return HttpResponse::Ok().body(
+                CampusesTemplate {
+                    campuses: db::get_campuses(&conn).unwrap(),
+                    error: format!("Riders or drivers signed up for upcoming events still use {code}"),
+                }
+                .render()
+                .unwrap(),
+            );
+        }
 
-    let conn = db::connect();
-    db::create_event(
-        &conn,
-        form.name.clone(),
-        time,
-        form.address1.clone(),
-        form.address2.clone().unwrap_or("".to_string()),
-        form.city.clone(),
-        form.state.clone(),
-        form.zipcode.clone(),
-        id,
-    )
-    .unwrap();
+        db::delete_campus(&conn, &code).unwrap();
+    }
 
     HttpResponse::SeeOther()
-        .append_header(("Location", "/"))
+        .append_header(("Location", "/campuses"))
         .finish()
 }
 
-#[get("/reset")]
-async fn get_reset_password() -> impl Responder {
-    ResetPasswordTemplate {}
-}
-
-#[derive(Deserialize)]
-struct RequestResetForm {
-    email: String
-}
-
-#[post("/reset")]
-async fn post_reset_password(form: web::Form<RequestResetForm>) -> impl Responder {
-    let conn = db::connect();
-
-    if let Ok(Some(user)) = db::get_user_by_email(&conn, form.email.clone()) {
-        let id = db::create_reset_request(&conn, user.id).unwrap();
-        send_reset_email(&form.email, &id.to_string()).await.ok();
-    }
-    
-    CheckEmailTemplate {}
-}
-
-#[get("/reset/{id}")]
-async fn get_reset_password_with_id(path: web::Path<(String,)>) -> impl Responder {
-    SetPasswordTemplate {}
-}
-
 #[derive(Deserialize)]
-struct ResetForm {
-    password: String
+struct PickupPointForm {
+    name: String,
+    area: String,
+    aliases: Option<String>,
 }
 
-#[post("/reset/{id}")]
-async fn post_reset_password_with_id(s: Session, path: web::Path<(String,)>, form: web::Form<ResetForm>) -> impl Responder {
-    let conn = db::connect();
+#[post("/campuses/{code}/pickup_points")]
+async fn post_pickup_point(s: Session, path: web::Path<(String,)>, form: web::Form<PickupPointForm>) -> impl Responder {
+    role!(s, Role::Admin);
 
-    if let Ok(id) = Uuid::parse_str(&path.0) {
-        if let Ok(Some(req)) = db::get_reset_request(&conn, id) {
-            db::set_password(&conn, req.user_id, form.password.clone()).unwrap();
+    // The point's own name always counts as an alias
+    let name = form.name.trim().to_string();
+    let aliases = match form.aliases.as_deref().map(str::trim) {
+        Some(aliases) if !aliases.is_empty() => format!("{name},{aliases}"),
+        _ => name.clone()
+    };
 
-            s.insert("logged_in", true).unwrap();
-            s.insert("user_id", req.user_id.to_string()).unwrap();
-        }
+    if let Ok(code) = path.0.parse::<CampusCode>() {
+        let conn = db::connect();
+        db::create_pickup_point(&conn, &code, name, form.area.trim().to_string(), aliases).unwrap();
     }
 
     HttpResponse::SeeOther()
-        .append_header(("Location", "/"))
+        .append_header(("Location", "/campuses"))
         .finish()
 }
 
-#[derive(Debug, Deserialize)]
-struct DeleteQuery {
-    event_id: String,
+#[derive(Deserialize)]
+struct DeletePickupPointForm {
+    name: String,
 }
 
-#[post("/events/delete")]
-async fn delete_event(s: Session, q: web::Query<DeleteQuery>, state: web::Data<AppState>) -> impl Responder {
-    auth!(s);
-
-    let id: String = s.get("user_id").unwrap().unwrap();
-    let id = Uuid::parse_str(id.as_str()).unwrap();
-
-    let event_id = Uuid::parse_str(q.event_id.as_str()).unwrap();
-
-    let conn = db::connect();
-    db::delete_user_event(&conn, id, event_id).unwrap();
+#[post("/campuses/{code}/pickup_points/delete")]
+async fn delete_pickup_point(s: Session, path: web::Path<(String,)>, form: web::Form<DeletePickupPointForm>) -> impl Responder {
+    role!(s, Role::Admin);
 
-    // Notify worker thread
-    state.tx.send(()).unwrap();
+    if let Ok(code) = path.0.parse::<CampusCode>() {
+        let conn = db::connect();
+        db::delete_pickup_point(&conn, &code, &form.name).unwrap();
+    }
 
     HttpResponse::SeeOther()
-        .append_header(("Location", "/"))
+        .append_header(("Location", "/campuses"))
         .finish()
 }
 
-#[get("/events/info")]
-async fn events_info(s: Session) -> impl Responder {
-    auth!(s);
+pub async fn start(tx: Sender<()>) -> std::io::Result<()> {
+    info!("Starting Webserver");
 
-    let conn = db::connect();
-    let events = db::get_events_info(&conn).unwrap();
+    let secret_key = session_key();
 
-    HttpResponse::Ok().body(
-        EventsInfoTemplate {
-            events
-        }
-        .render()
-        .unwrap()
-    )
-}
+    // Keep links made with the old shared INVITE_ID working until they're revoked
+    if let Some(id) = env::var("INVITE_ID").ok().and_then(|id| Uuid::parse_str(&id).ok()) {
+        db::create_invite(&db::connect(), id, None, None, None, None).unwrap();
+    }
 
-pub async fn start(tx: Sender<()>) -> std::io::Result<()> {
-    info!("Starting Webserver");
+    let (updates, outbox) = queue::unbounded_channel();
+    actix_web::rt::spawn(send_event_updates(outbox));
 
-    let secret_key = Key::generate();
+    let admin_email = env::var("ADMIN_EMAIL").ok();
 
     HttpServer::new(move || {
         App::new()
-            .app_data(web::Data::new(AppState { tx: tx.clone() }))
+            .app_data(web::Data::new(AppState {
+                tx: tx.clone(),
+                updates: updates.clone(),
+                admin_email: admin_email.clone(),
+            }))
+            .wrap_fn(|req, srv| {
+                check_device(&req);
+                srv.call(req)
+            })
             .wrap(Logger::new("%r"))
             .wrap(
                 SessionMiddleware::builder(CookieSessionStore::default(), secret_key.clone())
@@ -585,6 +2715,13 @@ pub async fn start(tx: Sender<()>) -> std::io::Result<()> {
                     .build(),
             )
             .service(get_root)
+            .service(get_devices)
+            .service(revoke_device)
+            .service(get_invites)
+            .service(post_invites)
+            .service(revoke_invite)
+            .service(get_users)
+            .service(post_users)
             .service(get_upcoming_events)
             .service(get_login)
             .service(post_login)
@@ -593,22 +2730,446 @@ pub async fn start(tx: Sender<()>) -> std::io::Result<()> {
             .service(post_vehicle)
             .service(get_signup)
             .service(post_signup)
+            .service(get_verify_email)
+            .service(resend_verification)
             .service(get_manage_events)
             .service(post_manage_events)
             .service(get_pickup)
             .service(post_pickup)
+            .service(get_group_invites)
+            .service(accept_group_invite)
+            .service(decline_group_invite)
             .service(get_seats)
             .service(post_seats)
             .service(get_css)
             .service(get_upcoming_events_js)
             .service(delete_event)
             .service(events_info)
+            .service(get_login_link)
+            .service(post_login_link)
+            .service(get_login_with_link)
+            .service(post_login_with_link)
             .service(get_reset_password_with_id)
             .service(get_reset_password)
             .service(post_reset_password_with_id)
             .service(post_reset_password)
+            .service(get_edit_event)
+            .service(post_edit_event)
+            .service(cancel_event)
+            .service(get_guest_ride)
+            .service(post_guest_ride)
+            .service(get_guest_status)
+            .service(get_edit_ride)
+            .service(post_edit_ride)
+            .service(get_edit_drive)
+            .service(post_edit_drive)
+            .service(get_edit_vehicle)
+            .service(post_edit_vehicle)
+            .service(delete_vehicle)
+            .service(get_matching)
+            .service(get_assignments)
+            .service(post_assignment)
+            .service(apply_matching)
+            .service(get_campuses)
+            .service(post_campus)
+            .service(delete_campus)
+            .service(post_pickup_point)
+            .service(delete_pickup_point)
     })
     .bind(("0.0.0.0", 8080))?
     .run()
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test;
+    use chrono::Timelike;
+    use std::sync::Once;
+
+    use crate::fixtures::{driver, event, ride, user_with_role};
+
+    static DATABASE: Once = Once::new();
+
+    /// Connect to a fresh database shared by every test here
+    fn setup() -> sqlite::Connection {
+        DATABASE.call_once(|| {
+            std::fs::remove_file(db::db_path()).ok();
+            db::create_database();
+        });
+        db::connect()
+    }
+
+    /// Tests share a database and run at the same time, so names must not repeat
+    fn unique() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    /// State for the app, along with what it sends the worker and attendees
+    fn state() -> (AppState, std::sync::mpsc::Receiver<()>, queue::UnboundedReceiver<EventUpdate>) {
+        let (tx, worker) = std::sync::mpsc::channel();
+        let (updates, outbox) = queue::unbounded_channel();
+        (AppState { tx, updates, admin_email: None }, worker, outbox)
+    }
+
+    /// Build the app with the given routes and a login route
+    macro_rules! app {
+        ($state:expr, $($service:expr),+) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new($state))
+                    .wrap(
+                        SessionMiddleware::builder(CookieSessionStore::default(), Key::generate())
+                            .cookie_secure(false)
+                            .build(),
+                    )
+                    .service(post_login)
+                    $(.service($service))+
+            )
+            .await
+        };
+    }
+
+    /// Log in as an existing user, returns the session cookie
+    macro_rules! login {
+        ($app:expr, $user_id:expr) => {{
+            let email = db::get_user(&db::connect(), $user_id).unwrap().unwrap().email;
+            let req = test::TestRequest::post()
+                .uri("/login")
+                .set_form([("email", email.as_str()), ("password", "password")])
+                .to_request();
+            let resp = test::call_service(&$app, req).await;
+            resp.response().cookies().next().unwrap().into_owned()
+        }};
+    }
+
+    /// Log in as a new user with a role, returns the session cookie
+    macro_rules! session {
+        ($app:expr, $role:expr) => {
+            login!($app, user_with_role(&db::connect(), &unique(), $role))
+        };
+    }
+
+    /// Whether a page let the user in instead of sending them home
+    macro_rules! allowed {
+        ($app:expr, $req:expr, $cookie:expr) => {{
+            let resp = test::call_service(&$app, $req.cookie($cookie.clone()).to_request()).await;
+            let home = resp.status() == StatusCode::SEE_OTHER
+                && resp.headers().get("Location").is_some_and(|location| location == "/");
+            !home
+        }};
+    }
+
+    #[actix_web::test]
+    async fn only_admins_manage_campuses() {
+        let conn = setup();
+        let (state, _worker, _outbox) = state();
+        let app = app!(state, get_campuses, post_campus, delete_campus);
+
+        for (role, expected) in [(Role::Member, false), (Role::Organizer, false), (Role::Admin, true)] {
+            let cookie = session!(app, role);
+            let code = format!("C{}", Uuid::new_v4().to_simple().to_string()[..6].to_uppercase());
+
+            assert_eq!(allowed!(app, test::TestRequest::get().uri("/campuses"), cookie), expected);
+
+            let create = test::TestRequest::post()
+                .uri("/campuses")
+                .set_form([("code", code.as_str()), ("name", "Test Campus")]);
+            assert_eq!(allowed!(app, create, cookie), expected);
+
+            let code = code.parse::<CampusCode>().unwrap();
+            assert_eq!(db::get_campus(&conn, &code).unwrap().is_some(), expected);
+        }
+    }
+
+    #[actix_web::test]
+    async fn admin_and_organizer_pages_check_the_role() {
+        setup();
+        let (state, _worker, _outbox) = state();
+        let app = app!(state, get_invites, get_users, get_manage_events, events_info);
+
+        let pages = [
+            ("/invites", Role::Admin),
+            ("/users", Role::Admin),
+            ("/manage_events", Role::Organizer),
+            ("/events/info", Role::Organizer)
+        ];
+
+        for role in Role::ALL {
+            let cookie = session!(app, role);
+
+            for (page, needed) in pages {
+                let req = test::TestRequest::get().uri(page);
+                assert_eq!(allowed!(app, req, cookie), role >= needed, "{page} as {role:?}");
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn only_organizers_preview_and_apply_matches() {
+        setup();
+        let (state, _worker, _outbox) = state();
+        let app = app!(state, get_matching, apply_matching);
+
+        for (role, expected) in [(Role::Member, false), (Role::Organizer, true), (Role::Admin, true)] {
+            let cookie = session!(app, role);
+
+            assert_eq!(allowed!(app, test::TestRequest::get().uri("/matching"), cookie), expected);
+
+            let apply = test::TestRequest::post()
+                .uri("/matching/apply")
+                .set_form([("plan", "")]);
+            assert_eq!(allowed!(app, apply, cookie), expected);
+        }
+    }
+
+    #[actix_web::test]
+    async fn only_organizers_change_assignments() {
+        let conn = setup();
+        let (state, _worker, _outbox) = state();
+        let app = app!(state, get_assignments, post_assignment);
+
+        let rider = user_with_role(&conn, &unique(), Role::Member);
+        let event = event(&conn, &unique(), rider);
+        ride(&conn, rider, event);
+
+        for (role, expected) in [(Role::Member, false), (Role::Organizer, true)] {
+            let cookie = session!(app, role);
+
+            let page = test::TestRequest::get().uri(&format!("/assignments?event_id={}", event));
+            assert_eq!(allowed!(app, page, cookie), expected);
+
+            let pin = test::TestRequest::post()
+                .uri("/assignments")
+                .set_form([
+                    ("event_id", event.to_string().as_str()),
+                    ("rider_id", rider.to_string().as_str()),
+                    ("leg", "TO"),
+                    ("action", "Pin"),
+                ]);
+            assert_eq!(allowed!(app, pin, cookie), expected);
+            assert_eq!(db::get_ride(&conn, event, rider, Leg::To).unwrap().unwrap().pinned, expected);
+        }
+    }
+
+    #[actix_web::test]
+    async fn admin_email_is_promoted_once_verified() {
+        let conn = setup();
+        let email = format!("{}@example.com", Uuid::new_v4());
+        let (mut state, _worker, _outbox) = state();
+        state.admin_email = Some(email.clone());
+        let app = app!(state, get_verify_email);
+
+        db::create_user(&conn, email.clone(), "Admin".into(), "password".into(), "5555555555".into()).unwrap();
+        let id = db::get_user_by_email(&conn, email.clone()).unwrap().unwrap().id;
+
+        // Logging in with an unverified address isn't enough
+        let login = test::TestRequest::post()
+            .uri("/login")
+            .set_form([("email", email.as_str()), ("password", "password")])
+            .to_request();
+        test::call_service(&app, login).await;
+        assert_eq!(db::get_user(&conn, id).unwrap().unwrap().role, Role::Member);
+
+        let token = db::create_verification(&conn, id).unwrap();
+        let verify = test::TestRequest::get().uri(&format!("/verify/{token}")).to_request();
+        test::call_service(&app, verify).await;
+        assert_eq!(db::get_user(&conn, id).unwrap().unwrap().role, Role::Admin);
+
+        // Logging in with the verified address promotes them again
+        db::set_role(&conn, id, Role::Member).unwrap();
+        login!(app, id);
+        assert_eq!(db::get_user(&conn, id).unwrap().unwrap().role, Role::Admin);
+    }
+
+    #[actix_web::test]
+    async fn admins_only_hand_out_member_roles() {
+        let conn = setup();
+        let (state, _worker, _outbox) = state();
+        let app = app!(state, post_users);
+        let cookie = session!(app, Role::Admin);
+        let member = user_with_role(&conn, &unique(), Role::Member);
+
+        for (role, expected) in [("GUEST", Role::Member), ("ORGANIZER", Role::Organizer)] {
+            let change = test::TestRequest::post()
+                .uri("/users")
+                .cookie(cookie.clone())
+                .set_form([("user_id", member.to_string().as_str()), ("role", role)])
+                .to_request();
+            test::call_service(&app, change).await;
+            assert_eq!(db::get_user(&conn, member).unwrap().unwrap().role, expected);
+        }
+    }
+
+    #[actix_web::test]
+    async fn vehicles_for_upcoming_drives_need_a_replacement() {
+        let conn = setup();
+        let (state, _worker, _outbox) = state();
+        let app = app!(state, delete_vehicle);
+
+        let id = user_with_role(&conn, &unique(), Role::Member);
+        let cookie = login!(app, id);
+
+        let event = event(&conn, &unique(), id);
+        driver(&conn, id, event, 3);
+        let old = db::get_driver_vehicles(&conn, id).unwrap()[0].id;
+        db::create_vehicle(&conn, id, "Red".into(), "Toyota".into(), "Camry".into()).unwrap();
+        let new = db::get_driver_vehicles(&conn, id).unwrap()
+            .into_iter()
+            .find(|vehicle| vehicle.id != old)
+            .unwrap()
+            .id;
+
+        let delete = |replacement: String| test::TestRequest::post()
+            .uri(&format!("/vehicles/{old}/delete"))
+            .cookie(cookie.clone())
+            .set_form([("replacement_id", replacement)])
+            .to_request();
+
+        test::call_service(&app, delete(String::new())).await;
+        assert!(db::get_vehicle(&conn, old).unwrap().is_some());
+        assert_eq!(db::get_user_drivers(&conn, event, id).unwrap()[0].vehicle_id, old);
+
+        test::call_service(&app, delete(new.to_string())).await;
+        assert!(db::get_vehicle(&conn, old).unwrap().is_none());
+        assert_eq!(db::get_user_drivers(&conn, event, id).unwrap()[0].vehicle_id, new);
+    }
+
+    /// The edit form for an event as it is now
+    fn edit_form(event: &Event) -> ManageEventForm {
+        ManageEventForm {
+            name: event.name.clone(),
+            date: event.time.format("%Y-%m-%d").to_string(),
+            time: event.time.format("%H:%M").to_string(),
+            address1: event.address1.clone(),
+            address2: Some(event.address2.clone()),
+            city: event.city.clone(),
+            state: event.state.clone(),
+            zipcode: event.zipcode.clone(),
+            strategy: event.strategy.clone(),
+            signup_deadline: None,
+            freeze_time: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn only_new_times_and_addresses_are_sent_to_attendees() {
+        let conn = setup();
+        let creator = user_with_role(&conn, &unique(), Role::Organizer);
+        let mut event = db::get_event(&conn, event(&conn, &unique(), creator)).unwrap().unwrap();
+        // The form only has minutes
+        event.time = event.time.with_second(0).unwrap();
+        let time = event.time;
+
+        let mut form = edit_form(&event);
+        form.name = "Renamed".into();
+        form.strategy = Some("balanced".into());
+        assert!(form.changes(&event, time).is_empty());
+
+        let later = time + chrono::Duration::hours(1);
+        assert!(form.changes(&event, later)[0].starts_with("New time: "));
+
+        form.address1 = "2 Main St".into();
+        let changes = form.changes(&event, time);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].starts_with("New address: 2 Main St"));
+    }
+
+    #[actix_web::test]
+    async fn organizers_move_events_for_riders_and_drivers() {
+        let conn = setup();
+        let (state, _worker, mut outbox) = state();
+        let app = app!(state, post_edit_event);
+
+        let creator = user_with_role(&conn, &unique(), Role::Organizer);
+        let cookie = login!(app, creator);
+        let event = db::get_event(&conn, event(&conn, &unique(), creator)).unwrap().unwrap();
+
+        let rider = user_with_role(&conn, &unique(), Role::Member);
+        ride(&conn, rider, event.id);
+        // Drives there and rides back
+        let driving = user_with_role(&conn, &unique(), Role::Member);
+        driver(&conn, driving, event.id, 3);
+        let campus = db::get_campus(&conn, &"RIT".parse().unwrap()).unwrap().unwrap();
+        db::create_ride(&conn, driving, event.id, &campus, "Gleason".into(), None, Leg::Return).unwrap();
+
+        let later = event.time.with_second(0).unwrap() + chrono::Duration::days(1);
+        let edit = test::TestRequest::post()
+            .uri(&format!("/manage_events/{}/edit", event.id))
+            .cookie(cookie)
+            .set_form([
+                ("name", event.name.as_str()),
+                ("date", later.format("%Y-%m-%d").to_string().as_str()),
+                ("time", later.format("%H:%M").to_string().as_str()),
+                ("address1", "2 Main St"),
+                ("address2", ""),
+                ("city", "Rochester"),
+                ("state", "NY"),
+                ("zipcode", "14623"),
+            ])
+            .to_request();
+        test::call_service(&app, edit).await;
+
+        let edited = db::get_event(&conn, event.id).unwrap().unwrap();
+        assert_eq!(edited.time, later);
+        assert_eq!(edited.address1, "2 Main St");
+
+        // Everyone signed up is told about the change once
+        let mut sent = Vec::new();
+        while let Ok(update) = outbox.try_recv() {
+            assert_eq!(update.changes.len(), 2);
+            sent.push(update.to);
+        }
+        sent.sort();
+
+        let email = |id| db::get_user(&conn, id).unwrap().unwrap().email;
+        let mut expected = vec![email(rider), email(driving)];
+        expected.sort();
+        assert_eq!(sent, expected);
+    }
+
+    #[actix_web::test]
+    async fn riders_ask_once_for_each_leg() {
+        let conn = setup();
+        let (state, _worker, _outbox) = state();
+        let app = app!(state, get_pickup, post_pickup);
+
+        let rider = user_with_role(&conn, &unique(), Role::Member);
+        let event = event(&conn, &unique(), rider);
+        ride(&conn, rider, event);
+
+        let cookie = login!(app, rider);
+        let req = test::TestRequest::get()
+            .uri(&format!("/pickup?event_id={event}"))
+            .cookie(cookie)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        let cookie = resp.response().cookies().next().unwrap().into_owned();
+
+        let pickup = |legs: &'static str| {
+            test::TestRequest::post()
+                .uri("/pickup")
+                .cookie(cookie.clone())
+                .set_form([("campus", "RIT"), ("pickup", "Gleason"), ("legs", legs)])
+                .to_request()
+        };
+
+        let resp = test::call_service(&app, pickup("SIDEWAYS")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(db::get_ride(&conn, event, rider, Leg::Return).unwrap().is_none());
+
+        // Only the leg they don't have yet is added
+        let resp = test::call_service(&app, pickup("BOTH")).await;
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        assert!(db::get_ride(&conn, event, rider, Leg::Return).unwrap().is_some());
+
+        let resp = test::call_service(&app, pickup("RETURN")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let campus = db::get_campus(&conn, &"RIT".parse().unwrap()).unwrap().unwrap();
+        let error = db::create_ride(&conn, rider, event, &campus, "Gleason".into(), None, Leg::To).unwrap_err();
+        assert!(db::is_constraint_error(error.as_ref()));
+    }
+}
@@ -1,19 +1,32 @@
+use std::env;
 use std::sync::mpsc::Receiver;
 
 use crate::db;
+use crate::matching;
 
 /// Start a new background thread which has a few different functions:
 /// 1. Find unassigned riders and assign them to available drivers
-/// 2. Find old events and delete them from the database
+/// 2. Find old events and expired login, reset and verification links and delete them from the database
 /// 3. Wait for updates
+///
+/// Setting AUTO_MATCH=false stops automatic matching, so leadership can
+/// preview and apply matches from /matching instead
 pub fn start(rx: Receiver<()>) {
     std::thread::spawn(move || {
+        let strategy = matching::configured_strategy();
+        let priority = matching::configured_priority();
+        let auto_match = env::var("AUTO_MATCH").map(|val| val != "false").unwrap_or(true);
 
         loop {
             {
                 let conn = db::connect();
                 db::delete_old_events(&conn).unwrap();
-                db::match_rides(&conn).unwrap();
+                db::delete_expired_login_tokens(&conn).unwrap();
+                db::delete_expired_resets(&conn).unwrap();
+                db::delete_expired_verifications(&conn).unwrap();
+                if auto_match {
+                    db::match_rides(&conn, strategy.as_ref(), priority).unwrap();
+                }
             }
             rx.recv().unwrap();
         }
@@ -0,0 +1,224 @@
+use chrono::{Duration, Local};
+use sqlite::Connection;
+use uuid::Uuid;
+
+use rides::db;
+use rides::models::Leg;
+
+mod common;
+use common::{driver, event, ride, setup, user};
+
+#[test]
+fn login_tokens_only_work_once() {
+    let conn = setup();
+    let a = user(&conn, "a");
+
+    let token = db::create_login_token(&conn, a).unwrap();
+    assert_eq!(db::redeem_login_token(&conn, token).unwrap(), Some(a));
+    assert_eq!(db::redeem_login_token(&conn, token).unwrap(), None);
+    assert_eq!(db::redeem_login_token(&conn, Uuid::new_v4()).unwrap(), None);
+}
+
+#[test]
+fn revoked_devices_stop_logging_in() {
+    let conn = setup();
+    let a = user(&conn, "a");
+    let b = user(&conn, "b");
+
+    let (device, token) = db::create_device(&conn, a, "Firefox").unwrap();
+    assert_eq!(db::get_device_by_token(&conn, token).unwrap().unwrap().user_id, a);
+
+    // Only the owner can revoke a device
+    db::delete_device(&conn, b, device).unwrap();
+    assert!(db::get_device(&conn, device).unwrap().is_some());
+
+    db::delete_device(&conn, a, device).unwrap();
+    assert!(db::get_device_by_token(&conn, token).unwrap().is_none());
+    assert!(db::get_user_devices(&conn, a).unwrap().is_empty());
+}
+
+fn sign_up(conn: &Connection, invite: Uuid, name: &str) -> Option<Uuid> {
+    let email = format!("{name}@example.com");
+    db::create_invited_user(conn, invite, email, name.into(), "password".into(), "5555555555".into()).unwrap()
+}
+
+#[test]
+fn invites_record_who_invited_whom() {
+    let conn = setup();
+    let admin = user(&conn, "admin");
+    let invite = Uuid::new_v4();
+    db::create_invite(&conn, invite, Some(admin), None, None, Some(1)).unwrap();
+
+    let now = Local::now().naive_local();
+    assert!(db::get_invite(&conn, invite).unwrap().unwrap().check(now).is_ok());
+
+    let a = sign_up(&conn, invite, "a").unwrap();
+    assert!(db::get_invite(&conn, invite).unwrap().unwrap().check(now).is_err());
+
+    let invited_by = db::get_users(&conn).unwrap()
+        .into_iter()
+        .find(|(user, _)| user.id == a)
+        .map(|(_, invited_by)| invited_by);
+    assert_eq!(invited_by.as_deref(), Some("admin"));
+
+    let other = Uuid::new_v4();
+    db::create_invite(&conn, other, Some(admin), None, None, None).unwrap();
+    db::revoke_invite(&conn, other).unwrap();
+    assert!(db::get_invite(&conn, other).unwrap().unwrap().check(now).is_err());
+}
+
+#[test]
+fn used_up_invites_create_no_accounts() {
+    let conn = setup();
+    let admin = user(&conn, "admin");
+
+    // Both sign ups passed the check before either used the invite
+    let invite = Uuid::new_v4();
+    db::create_invite(&conn, invite, Some(admin), None, None, Some(1)).unwrap();
+    assert!(sign_up(&conn, invite, "a").is_some());
+    assert_eq!(sign_up(&conn, invite, "b"), None);
+    assert!(db::get_user_by_email(&conn, "b@example.com".into()).unwrap().is_none());
+    assert_eq!(db::get_invite(&conn, invite).unwrap().unwrap().uses, 1);
+
+    let revoked = Uuid::new_v4();
+    db::create_invite(&conn, revoked, Some(admin), None, None, None).unwrap();
+    db::revoke_invite(&conn, revoked).unwrap();
+    assert_eq!(sign_up(&conn, revoked, "c"), None);
+
+    let expired = Uuid::new_v4();
+    let yesterday = Local::now().naive_local() - Duration::days(1);
+    db::create_invite(&conn, expired, Some(admin), None, Some(yesterday), None).unwrap();
+    assert_eq!(sign_up(&conn, expired, "d"), None);
+
+    // A taken email leaves the invite unused
+    let open = Uuid::new_v4();
+    db::create_invite(&conn, open, Some(admin), None, None, Some(1)).unwrap();
+    let taken = db::create_invited_user(&conn, open, "A@example.com".into(), "A".into(), "password".into(), "5555555555".into());
+    assert!(taken.is_err_and(|error| db::is_constraint_error(error.as_ref())));
+    assert_eq!(db::get_invite(&conn, open).unwrap().unwrap().uses, 0);
+    assert!(sign_up(&conn, open, "e").is_some());
+}
+
+#[test]
+fn emails_are_unique_and_verified_by_link() {
+    let conn = setup();
+    let a = user(&conn, "a");
+
+    assert_eq!(db::get_user_by_email(&conn, "A@Example.com".into()).unwrap().unwrap().id, a);
+    let duplicate = db::create_user(&conn, "A@example.com".into(), "A".into(), "password".into(), "5555555555".into());
+    assert!(duplicate.is_err_and(|error| db::is_constraint_error(error.as_ref())));
+
+    assert!(!db::get_user(&conn, a).unwrap().unwrap().verified);
+    let token = db::create_verification(&conn, a).unwrap();
+    assert_eq!(db::verify_email(&conn, token).unwrap(), Some(a));
+    assert!(db::get_user(&conn, a).unwrap().unwrap().verified);
+    assert_eq!(db::verify_email(&conn, token).unwrap(), None);
+}
+
+#[test]
+fn verification_links_expire() {
+    let conn = setup();
+    let a = user(&conn, "a");
+
+    let old = db::create_verification(&conn, a).unwrap();
+    conn.execute(format!("UPDATE verifications SET request_time = 0 WHERE token_id = '{old}';")).unwrap();
+    assert_eq!(db::verify_email(&conn, old).unwrap(), None);
+    assert!(!db::get_user(&conn, a).unwrap().unwrap().verified);
+
+    // Expired links are cleaned up, newer ones are kept
+    let new = db::create_verification(&conn, a).unwrap();
+    db::delete_expired_verifications(&conn).unwrap();
+    let mut stmt = conn.prepare("SELECT token_id FROM verifications WHERE user_id = ?").unwrap();
+    stmt.bind(1, &*a.to_string()).unwrap();
+    stmt.next().unwrap();
+    assert_eq!(stmt.read::<String>(0).unwrap(), new.to_string());
+    assert_eq!(stmt.next().unwrap(), sqlite::State::Done);
+}
+
+#[test]
+fn duplicate_emails_merge_into_the_oldest_account() {
+    let conn = setup();
+    let a = user(&conn, "a");
+    let next = event(&conn, "Large Group", a);
+
+    // Go back to before emails were unique to add a duplicate account
+    conn.execute("
+        DROP INDEX users_email;
+        DROP TABLE verifications;
+        ALTER TABLE users DROP COLUMN verified;
+        PRAGMA user_version = 16;
+    ").unwrap();
+
+    let duplicate = Uuid::new_v4();
+    conn.execute(format!("
+        INSERT INTO users (id, email, fullname, password, number, rides_taken)
+        VALUES ('{duplicate}', ' A@Example.com ', 'A', '', '5555555555', 2);
+    ")).unwrap();
+    ride(&conn, duplicate, next);
+
+    db::migrate(&conn).unwrap();
+
+    assert!(db::get_user(&conn, duplicate).unwrap().is_none());
+    assert_eq!(db::get_ride(&conn, next, a, Leg::To).unwrap().unwrap().rider_id, a);
+
+    let mut stmt = conn.prepare("SELECT rides_taken FROM users WHERE id = ?").unwrap();
+    stmt.bind(1, &*a.to_string()).unwrap();
+    stmt.next().unwrap();
+    assert_eq!(stmt.read::<i64>(0).unwrap(), 2);
+    assert!(db::get_user(&conn, a).unwrap().unwrap().verified);
+}
+
+#[test]
+fn passengers_of_merged_drives_are_matched_again() {
+    let conn = setup();
+    let a = user(&conn, "a");
+    let next = event(&conn, "Large Group", a);
+    driver(&conn, a, next, 1);
+    let passenger = user(&conn, "passenger");
+    ride(&conn, passenger, next);
+
+    // Go back to before emails were unique to add a duplicate account
+    conn.execute("
+        DROP INDEX users_email;
+        DROP TABLE verifications;
+        ALTER TABLE users DROP COLUMN verified;
+        PRAGMA user_version = 16;
+    ").unwrap();
+
+    let duplicate = Uuid::new_v4();
+    conn.execute(format!("
+        INSERT INTO users (id, email, fullname, password, number, rides_taken)
+        VALUES ('{duplicate}', 'a@example.com', 'A', '', '5555555555', 0);
+    ")).unwrap();
+    driver(&conn, duplicate, next, 4);
+    db::set_assignment(&conn, next, passenger, Leg::To, Some(duplicate), false, duplicate).unwrap();
+
+    db::migrate(&conn).unwrap();
+
+    // The kept car only has one seat, so the duplicate's passenger needs a new one
+    let ride = db::get_ride(&conn, next, passenger, Leg::To).unwrap().unwrap();
+    assert_eq!(ride.driver_id, None);
+    assert!(ride.driver_cancelled);
+    assert_eq!(db::get_user_drivers(&conn, next, a).unwrap().len(), 1);
+}
+
+#[test]
+fn reset_links_work_once_and_replace_older_links() {
+    let conn = setup();
+    let a = user(&conn, "a");
+
+    let first = db::create_reset_request(&conn, a).unwrap();
+    let second = db::create_reset_request(&conn, a).unwrap();
+    assert!(db::get_reset_request(&conn, first).unwrap().is_none());
+
+    assert_eq!(db::redeem_reset_request(&conn, second).unwrap().unwrap().user_id, a);
+    assert!(db::redeem_reset_request(&conn, second).unwrap().is_none());
+
+    // Expired links are cleaned up
+    let old = db::create_reset_request(&conn, a).unwrap();
+    conn.execute(format!("UPDATE resets SET request_time = 0 WHERE reset_id = '{old}';")).unwrap();
+    let now = Local::now().naive_local();
+    assert!(db::get_reset_request(&conn, old).unwrap().unwrap().is_expired(now));
+    db::delete_expired_resets(&conn).unwrap();
+    assert!(db::get_reset_request(&conn, old).unwrap().is_none());
+}
@@ -0,0 +1,80 @@
+//! Fixtures shared by the integration tests and the route tests in `webserver`.
+// Each test file only uses some of these
+#![allow(dead_code)]
+
+use chrono::{Duration, Local};
+use sqlite::Connection;
+use uuid::Uuid;
+
+use rides::db;
+use rides::models::{CampusCode, Leg, Role};
+
+/// Fresh in-memory database with every migration applied
+pub fn setup() -> Connection {
+    let conn = sqlite::open(":memory:").unwrap();
+    db::migrate(&conn).unwrap();
+    conn
+}
+
+pub fn rit() -> CampusCode {
+    "RIT".parse().unwrap()
+}
+
+pub fn user(conn: &Connection, name: &str) -> Uuid {
+    let email = format!("{name}@example.com");
+    db::create_user(conn, email.clone(), name.into(), "password".into(), "5555555555".into()).unwrap();
+    db::get_user_by_email(conn, email).unwrap().unwrap().id
+}
+
+/// A verified user with a role, like an account leadership has set up
+pub fn user_with_role(conn: &Connection, name: &str, role: Role) -> Uuid {
+    let id = user(conn, name);
+    db::set_role(conn, id, role).unwrap();
+    db::set_verified(conn, id).unwrap();
+    id
+}
+
+pub fn event(conn: &Connection, name: &str, creator: Uuid) -> Uuid {
+    event_at(conn, name, creator, Duration::days(1))
+}
+
+pub fn event_at(conn: &Connection, name: &str, creator: Uuid, from_now: Duration) -> Uuid {
+    let time = Local::now().naive_local() + from_now;
+    db::create_event(
+        conn,
+        name.into(),
+        time,
+        "1 Main St".into(),
+        "".into(),
+        "Rochester".into(),
+        "NY".into(),
+        "14623".into(),
+        creator,
+        None,
+        None,
+        None
+    ).unwrap();
+
+    db::get_events(conn).unwrap()
+        .into_iter()
+        .find(|event| event.name == name)
+        .unwrap()
+        .id
+}
+
+pub fn driver(conn: &Connection, driver_id: Uuid, event_id: Uuid, seats: usize) {
+    let vehicle_id = match db::get_driver_vehicles(conn, driver_id).unwrap().pop() {
+        Some(vehicle) => vehicle.id,
+        None => {
+            db::create_vehicle(conn, driver_id, "Blue".into(), "Honda".into(), "Civic".into()).unwrap();
+            db::get_driver_vehicles(conn, driver_id).unwrap()[0].id
+        }
+    };
+
+    db::create_driver(conn, driver_id, event_id, vehicle_id, seats, &[rit()], Leg::To).unwrap();
+}
+
+pub fn ride(conn: &Connection, rider_id: Uuid, event_id: Uuid) {
+    let campus = db::get_campus(conn, &rit()).unwrap().unwrap();
+    db::create_ride(conn, rider_id, event_id, &campus, "Gleason".into(), None, Leg::To).unwrap();
+}
@@ -0,0 +1,518 @@
+use chrono::{Duration, Local};
+use sqlite::Connection;
+use uuid::Uuid;
+
+use rides::db;
+use rides::matching::{get_strategy, Greedy};
+use rides::matching::WaitlistPriority::{FirstCome, FirstTime};
+use rides::models::{CampusCode, Leg};
+
+mod common;
+use common::{driver, event, event_at, rit, ride, setup, user};
+
+fn driver_of(conn: &Connection, rider_id: Uuid, event_id: Uuid) -> Option<Uuid> {
+    db::get_ride(conn, event_id, rider_id, Leg::To).unwrap().unwrap().driver_id
+}
+
+#[test]
+fn seats_taken_are_counted_per_event() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let first = event(&conn, "Large Group", d);
+    let second = event(&conn, "Worship Night", d);
+
+    driver(&conn, d, first, 2);
+    driver(&conn, d, second, 2);
+
+    for name in ["a", "b"] {
+        let rider = user(&conn, name);
+        ride(&conn, rider, first);
+    }
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    let taken = |event_id| {
+        db::get_available_drivers(&conn, event_id, Leg::To, &rit()).unwrap()
+            .iter()
+            .map(|(_, taken)| *taken)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(taken(first), vec![2]);
+    assert_eq!(taken(second), vec![0]);
+}
+
+#[test]
+fn passengers_for_another_event_do_not_fill_the_car() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let first = event(&conn, "Large Group", d);
+    let second = event(&conn, "Worship Night", d);
+
+    driver(&conn, d, first, 2);
+    driver(&conn, d, second, 2);
+
+    let riders: Vec<Uuid> = ["a", "b", "c", "e"].iter().map(|name| user(&conn, name)).collect();
+    ride(&conn, riders[0], first);
+    ride(&conn, riders[1], first);
+    ride(&conn, riders[2], second);
+    ride(&conn, riders[3], second);
+
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    assert_eq!(driver_of(&conn, riders[0], first), Some(d));
+    assert_eq!(driver_of(&conn, riders[1], first), Some(d));
+    assert_eq!(driver_of(&conn, riders[2], second), Some(d));
+    assert_eq!(driver_of(&conn, riders[3], second), Some(d));
+}
+
+#[test]
+fn seat_limits_hold_for_each_event() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let first = event(&conn, "Large Group", d);
+    let second = event(&conn, "Worship Night", d);
+
+    driver(&conn, d, first, 1);
+    driver(&conn, d, second, 3);
+
+    let riders: Vec<Uuid> = ["a", "b", "c"].iter().map(|name| user(&conn, name)).collect();
+    for &rider in &riders {
+        ride(&conn, rider, first);
+        ride(&conn, rider, second);
+    }
+
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    let seated = |event_id| {
+        riders.iter()
+            .filter(|&&rider| driver_of(&conn, rider, event_id).is_some())
+            .count()
+    };
+
+    assert_eq!(seated(first), 1);
+    assert_eq!(seated(second), 3);
+}
+
+#[test]
+fn matching_again_keeps_existing_assignments() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let first = event(&conn, "Large Group", d);
+    let second = event(&conn, "Worship Night", d);
+
+    driver(&conn, d, first, 1);
+    driver(&conn, d, second, 1);
+
+    let a = user(&conn, "a");
+    ride(&conn, a, first);
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    // A later sign up for the other event still gets the driver's seat there
+    let b = user(&conn, "b");
+    let c = user(&conn, "c");
+    ride(&conn, b, second);
+    ride(&conn, c, first);
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    assert_eq!(driver_of(&conn, a, first), Some(d));
+    assert_eq!(driver_of(&conn, b, second), Some(d));
+    assert_eq!(driver_of(&conn, c, first), None);
+}
+
+#[test]
+fn preview_does_not_assign() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let first = event(&conn, "Large Group", d);
+    driver(&conn, d, first, 2);
+
+    let a = user(&conn, "a");
+    ride(&conn, a, first);
+
+    let preview = db::preview_matches(&conn, &Greedy, FirstCome).unwrap();
+    assert_eq!(preview.len(), 1);
+    assert_eq!(preview[0].rider_id, a);
+    assert_eq!(driver_of(&conn, a, first), None);
+
+    let applied = db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+    assert_eq!(applied.len(), 1);
+    assert_eq!(driver_of(&conn, a, first), Some(d));
+}
+
+#[test]
+fn pinned_rides_are_left_alone() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let first = event(&conn, "Large Group", d);
+    driver(&conn, d, first, 2);
+
+    let a = user(&conn, "a");
+    let b = user(&conn, "b");
+    ride(&conn, a, first);
+    ride(&conn, b, first);
+
+    // Leadership keeps a out of every car
+    db::set_assignment(&conn, first, a, Leg::To, None, true, d).unwrap();
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    assert_eq!(driver_of(&conn, a, first), None);
+    assert_eq!(driver_of(&conn, b, first), Some(d));
+    assert_eq!(db::get_assignment_changes(&conn, first).unwrap().len(), 1);
+
+    db::set_assignment(&conn, first, a, Leg::To, None, false, d).unwrap();
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    assert_eq!(driver_of(&conn, a, first), Some(d));
+}
+
+#[test]
+fn waitlist_is_first_come_first_served() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let first = event(&conn, "Large Group", d);
+    driver(&conn, d, first, 1);
+
+    let a = user(&conn, "a");
+    let b = user(&conn, "b");
+    ride(&conn, a, first);
+    ride(&conn, b, first);
+
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    assert_eq!(driver_of(&conn, a, first), Some(d));
+    assert_eq!(driver_of(&conn, b, first), None);
+
+    let waiting = db::get_events_data(&conn, b, FirstCome).unwrap();
+    assert_eq!(waiting[0].legs[0].waitlist_position, Some(1));
+}
+
+#[test]
+fn first_time_riders_can_go_first() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let a = user(&conn, "a");
+    let b = user(&conn, "b");
+
+    // a got a ride to an event that has since passed
+    let past = event_at(&conn, "Last Week", d, Duration::days(-7));
+    driver(&conn, d, past, 1);
+    ride(&conn, a, past);
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+    db::delete_old_events(&conn).unwrap();
+
+    let next = event(&conn, "Large Group", d);
+    driver(&conn, d, next, 1);
+    ride(&conn, a, next);
+    ride(&conn, b, next);
+
+    db::match_rides(&conn, &Greedy, FirstTime).unwrap();
+
+    assert_eq!(driver_of(&conn, a, next), None);
+    assert_eq!(driver_of(&conn, b, next), Some(d));
+}
+
+#[test]
+fn riders_whose_driver_cancels_are_reassigned_first() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let next = event(&conn, "Large Group", d);
+    driver(&conn, d, next, 2);
+
+    let a = user(&conn, "a");
+    let b = user(&conn, "b");
+    ride(&conn, a, next);
+    ride(&conn, b, next);
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    // c is already waiting when a smaller car signs up
+    let c = user(&conn, "c");
+    ride(&conn, c, next);
+    let backup = user(&conn, "backup");
+    driver(&conn, backup, next, 1);
+
+    db::delete_user_event(&conn, d, next).unwrap();
+    let stranded: Vec<Uuid> = db::reassign_cancelled_rides(&conn, next, &Greedy, FirstCome).unwrap()
+        .iter()
+        .map(|ride| ride.rider_id)
+        .collect();
+
+    assert_eq!(stranded.len(), 1);
+    let moved = if stranded[0] == a { b } else { a };
+    assert!([a, b].contains(&stranded[0]));
+    assert_eq!(driver_of(&conn, moved, next), Some(backup));
+    assert_eq!(driver_of(&conn, stranded[0], next), None);
+    assert_eq!(driver_of(&conn, c, next), None);
+}
+
+#[test]
+fn frozen_events_are_not_matched() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let now = Local::now().naive_local();
+
+    db::create_event(
+        &conn,
+        "Retreat".into(),
+        now + Duration::days(1),
+        "1 Main St".into(),
+        "".into(),
+        "Rochester".into(),
+        "NY".into(),
+        "14623".into(),
+        d,
+        None,
+        Some(now - Duration::hours(2)),
+        Some(now - Duration::hours(1))
+    ).unwrap();
+    let retreat = db::get_events(&conn).unwrap()[0].id;
+
+    driver(&conn, d, retreat, 1);
+    let a = user(&conn, "a");
+    ride(&conn, a, retreat);
+
+    assert!(db::preview_matches(&conn, &Greedy, FirstCome).unwrap().is_empty());
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+    assert_eq!(driver_of(&conn, a, retreat), None);
+}
+
+#[test]
+fn fewer_seats_bump_unpinned_riders() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let next = event(&conn, "Large Group", d);
+
+    driver(&conn, d, next, 3);
+    let riders: Vec<Uuid> = ["a", "b", "c"].iter().map(|name| user(&conn, name)).collect();
+    for rider in &riders {
+        ride(&conn, *rider, next);
+    }
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+    db::set_assignment(&conn, next, riders[2], Leg::To, Some(d), true, d).unwrap();
+
+    let vehicle_id = db::get_driver_vehicles(&conn, d).unwrap()[0].id;
+    assert!(!db::update_driver(&conn, d, next, vehicle_id, 3, Leg::To).unwrap());
+    assert!(db::update_driver(&conn, d, next, vehicle_id, 1, Leg::To).unwrap());
+
+    assert_eq!(driver_of(&conn, riders[0], next), None);
+    assert_eq!(driver_of(&conn, riders[1], next), None);
+    assert_eq!(driver_of(&conn, riders[2], next), Some(d));
+}
+
+#[test]
+fn drivers_change_seats_one_leg_at_a_time() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let next = event(&conn, "Large Group", d);
+
+    driver(&conn, d, next, 4);
+    let vehicle_id = db::get_driver_vehicles(&conn, d).unwrap()[0].id;
+    db::create_driver(&conn, d, next, vehicle_id, 2, &[rit()], Leg::Return).unwrap();
+
+    db::update_driver(&conn, d, next, vehicle_id, 3, Leg::To).unwrap();
+
+    let seats: Vec<(Leg, i64)> = db::get_user_drivers(&conn, next, d).unwrap()
+        .into_iter()
+        .map(|driver| (driver.leg, driver.seats))
+        .collect();
+    assert!(seats.contains(&(Leg::To, 3)));
+    assert!(seats.contains(&(Leg::Return, 2)));
+}
+
+#[test]
+fn cancelled_events_are_kept_without_sign_ups() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let a = user(&conn, "a");
+    let next = event(&conn, "Large Group", d);
+
+    driver(&conn, d, next, 1);
+    ride(&conn, a, next);
+    db::cancel_event(&conn, next, d).unwrap();
+
+    assert!(db::get_events(&conn).unwrap().is_empty());
+    assert!(db::get_event_attendees(&conn, next).unwrap().is_empty());
+    assert!(db::match_rides(&conn, &Greedy, FirstCome).unwrap().is_empty());
+
+    let cancelled = db::get_cancelled_events(&conn).unwrap();
+    assert_eq!(cancelled.len(), 1);
+    assert_eq!(cancelled[0].cancelled_by, Some(d));
+}
+
+#[test]
+fn campuses_in_use_are_kept() {
+    let conn = setup();
+    let a = user(&conn, "a");
+    let next = event(&conn, "Large Group", a);
+    let code: CampusCode = "GV".parse().unwrap();
+
+    db::create_campus(&conn, &code, "Geneseo".into()).unwrap();
+    db::create_pickup_point(&conn, &code, "Union".into(), "Union".into(), "".into()).unwrap();
+    let campus = db::get_campus(&conn, &code).unwrap().unwrap();
+    db::create_ride(&conn, a, next, &campus, "Union".into(), None, Leg::To).unwrap();
+    assert_eq!(db::count_campus_signups(&conn, &code).unwrap(), 1);
+
+    db::cancel_event(&conn, next, a).unwrap();
+    assert_eq!(db::count_campus_signups(&conn, &code).unwrap(), 0);
+
+    db::delete_campus(&conn, &code).unwrap();
+    assert!(db::get_campus(&conn, &code).unwrap().is_none());
+
+    // Pickup points don't come back with a campus that reuses the code
+    db::create_campus(&conn, &code, "Geneseo".into()).unwrap();
+    assert!(db::get_campus(&conn, &code).unwrap().unwrap().pickup_points.is_empty());
+}
+
+#[test]
+fn events_info_keeps_events_with_the_same_name_apart() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let a = user(&conn, "a");
+    let b = user(&conn, "b");
+    let first = event_at(&conn, "Large Group", d, Duration::days(1));
+    db::create_event(
+        &conn,
+        "Large Group".into(),
+        Local::now().naive_local() + Duration::days(8),
+        "1 Main St".into(),
+        "".into(),
+        "Rochester".into(),
+        "NY".into(),
+        "14623".into(),
+        d,
+        None,
+        None,
+        None
+    ).unwrap();
+    let second = db::get_events(&conn).unwrap()[1].id;
+
+    driver(&conn, d, first, 1);
+    ride(&conn, a, first);
+    ride(&conn, b, second);
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    let info = db::get_events_info(&conn).unwrap();
+    assert_eq!(info.len(), 2);
+
+    assert_eq!(info[0].event.id, first);
+    assert_eq!(info[0].cars.len(), 1);
+    assert_eq!(info[0].cars[0].passengers[0].0.id, a);
+    assert!(info[0].unassigned.is_empty());
+
+    assert_eq!(info[1].event.id, second);
+    assert!(info[1].cars.is_empty());
+    assert_eq!(info[1].unassigned[0].0.id, b);
+}
+
+#[test]
+fn group_invites_change_nothing_until_accepted() {
+    let conn = setup();
+    let small = user(&conn, "small");
+    let large = user(&conn, "large");
+    let a = user(&conn, "a");
+    let b = user(&conn, "b");
+    let next = event(&conn, "Large Group", small);
+
+    driver(&conn, small, next, 1);
+    ride(&conn, b, next);
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+    assert_eq!(driver_of(&conn, b, next), Some(small));
+
+    let campus = db::get_campus(&conn, &rit()).unwrap().unwrap();
+    db::create_group_ride(&conn, a, next, &campus, "Gleason".into(), &[b], Leg::To).unwrap();
+
+    // Asking to ride with someone leaves their ride alone
+    let ride_b = db::get_ride(&conn, next, b, Leg::To).unwrap().unwrap();
+    assert_eq!(ride_b.driver_id, Some(small));
+    assert_eq!(ride_b.group_id, None);
+
+    let invites = db::get_user_group_invites(&conn, b).unwrap();
+    assert_eq!(invites.len(), 1);
+    db::accept_group_invite(&conn, &invites[0], &campus, "Gleason".into()).unwrap();
+    assert!(db::get_user_group_invites(&conn, b).unwrap().is_empty());
+
+    driver(&conn, large, next, 2);
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+    assert_eq!(driver_of(&conn, a, next), Some(large));
+    assert_eq!(driver_of(&conn, b, next), Some(large));
+}
+
+#[test]
+fn groups_too_big_for_any_car_do_not_hold_up_the_waitlist() {
+    let conn = setup();
+    let first = user(&conn, "first");
+    let second = user(&conn, "second");
+    let leader = user(&conn, "leader");
+    let friends = [user(&conn, "friend1"), user(&conn, "friend2")];
+    let solos = [user(&conn, "solo1"), user(&conn, "solo2")];
+    let next = event(&conn, "Large Group", first);
+
+    driver(&conn, first, next, 2);
+    driver(&conn, second, next, 2);
+
+    let campus = db::get_campus(&conn, &rit()).unwrap().unwrap();
+    db::create_group_ride(&conn, leader, next, &campus, "Gleason".into(), &friends, Leg::To).unwrap();
+    for friend in friends {
+        let invite = db::get_user_group_invites(&conn, friend).unwrap().pop().unwrap();
+        db::accept_group_invite(&conn, &invite, &campus, "Gleason".into()).unwrap();
+    }
+    for solo in solos {
+        ride(&conn, solo, next);
+    }
+
+    for strategy in ["greedy", "balanced", "clustered"] {
+        let strategy = get_strategy(strategy).unwrap();
+        db::match_rides(&conn, strategy.as_ref(), FirstCome).unwrap();
+    }
+
+    for solo in solos {
+        assert!(driver_of(&conn, solo, next).is_some());
+    }
+    assert_eq!(driver_of(&conn, leader, next), None);
+}
+
+#[test]
+fn guests_are_matched_like_any_rider() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let next = event(&conn, "Large Group", d);
+
+    let (guest, token) = db::create_guest(&conn, "Guest".into(), "5555555555".into()).unwrap();
+    driver(&conn, d, next, 1);
+    ride(&conn, guest, next);
+    db::match_rides(&conn, &Greedy, FirstCome).unwrap();
+
+    assert_eq!(driver_of(&conn, guest, next), Some(d));
+    assert_eq!(db::get_guest(&conn, token).unwrap().unwrap().id, guest);
+    assert!(db::get_users(&conn).unwrap().iter().all(|(user, _)| user.id != guest));
+    assert!(db::get_event_attendees(&conn, next).unwrap().iter().all(|user| user.id != guest));
+}
+
+#[test]
+fn repeat_sign_ups_keep_the_matched_ride() {
+    let conn = setup();
+    let d = user(&conn, "driver");
+    let r = user(&conn, "rider");
+    let next = event(&conn, "Large Group", d);
+    driver(&conn, d, next, 4);
+
+    // Go back to before riders had one ride per leg to sign up twice
+    conn.execute("
+        DROP INDEX rides_event_rider_leg;
+        PRAGMA user_version = 18;
+    ").unwrap();
+
+    ride(&conn, r, next);
+    ride(&conn, r, next);
+    conn.execute(format!("
+        UPDATE rides SET driver_id = '{d}'
+        WHERE rowid = (SELECT MAX(rowid) FROM rides WHERE rider_id = '{r}');
+    ")).unwrap();
+
+    db::migrate(&conn).unwrap();
+
+    assert_eq!(driver_of(&conn, r, next), Some(d));
+    let mut stmt = conn.prepare("SELECT COUNT(*) FROM rides WHERE rider_id = ?").unwrap();
+    stmt.bind(1, &*r.to_string()).unwrap();
+    stmt.next().unwrap();
+    assert_eq!(stmt.read::<i64>(0).unwrap(), 1);
+}